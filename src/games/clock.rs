@@ -0,0 +1,23 @@
+//! Where both games read the time. The page's clocks by default; a game
+//! built headless takes any clock, so tests can step time themselves.
+
+use std::rc::Rc;
+
+/// Milliseconds since some fixed point, which only has to stay put for as
+/// long as the game runs.
+pub type Clock = Rc<dyn Fn() -> f64>;
+
+/// The page's wall clock, `Date.now()`.
+pub fn wall() -> Clock {
+    Rc::new(js_sys::Date::now)
+}
+
+/// Milliseconds since the page loaded, `performance.now()`, which the
+/// user cannot set back.
+pub fn monotonic() -> Clock {
+    let performance = web_sys::window()
+        .expect("no global window exists")
+        .performance()
+        .expect("performance should be available");
+    Rc::new(move || performance.now())
+}
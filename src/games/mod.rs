@@ -1,2 +1,6 @@
+pub mod clock;
 pub mod numeracy;
 pub mod perception;
+pub mod renderer;
+pub(crate) mod rng;
+pub mod storage;
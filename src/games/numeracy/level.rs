@@ -13,9 +13,9 @@ impl Level {
         }
     }
 
-    pub fn generate_expressions(&self) -> Vec<Expression> {
+    pub fn generate_expressions(&self, random: &mut dyn FnMut() -> f64) -> Vec<Expression> {
         (0..self.expressions_per_round)
-            .map(|_| Expression::new(self.number))
+            .map(|_| Expression::new(self.number, random))
             .collect()
     }
 
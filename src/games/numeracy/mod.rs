@@ -1,7 +1,14 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
-use web_sys::{Document, Element, Event, HtmlElement};
+use web_sys::{Event, HtmlElement};
+
+use super::{
+    clock::{self, Clock},
+    renderer::{CellView, DomRenderer, SharedRenderer},
+    rng::Rng,
+    storage::{self, SharedStorage},
+};
 
 mod expression;
 mod level;
@@ -12,10 +19,12 @@ use level::Level;
 use state::GameState;
 
 #[wasm_bindgen]
+#[derive(Clone)]
 pub struct Numeracy {
     state: Rc<RefCell<GameState>>, // Changed to Rc<RefCell<>> for shared ownership
-    document: Document,
-    container: HtmlElement,
+    // Where the bubbles are on the page; None when the game runs headless
+    container: Option<HtmlElement>,
+    renderer: SharedRenderer,
 }
 
 #[wasm_bindgen]
@@ -29,10 +38,19 @@ impl Numeracy {
             .unwrap()
             .dyn_into::<HtmlElement>()?;
 
+        let state = GameState::new(
+            storage::shared(),
+            clock::monotonic(),
+            Box::new(js_sys::Math::random),
+        );
         let game = Numeracy {
-            state: Rc::new(RefCell::new(GameState::new())),
-            document: document.clone(),
-            container,
+            state: Rc::new(RefCell::new(state)),
+            container: Some(container),
+            renderer: Rc::new(RefCell::new(DomRenderer::new(
+                document.clone(),
+                "game-container",
+                "bubble",
+            ))),
         };
 
         // Set up visibility change handler
@@ -42,7 +60,7 @@ impl Numeracy {
             let hidden = doc.hidden();
             let mut game_state = state.borrow_mut();
             game_state.is_visible = !hidden;
-            
+
             if !hidden {
                 // When becoming visible, restart the current level
                 game_state.start_round();
@@ -55,52 +73,49 @@ impl Numeracy {
         Ok(game)
     }
 
+    fn bubble_view(state: &GameState, index: usize) -> Option<CellView> {
+        let expr = state.expressions.get(index)?;
+        let classes = if state.selected_indices.contains(&index) {
+            vec!["selected".to_string()]
+        } else {
+            Vec::new()
+        };
+        Some(CellView {
+            text: expr.text.clone(),
+            classes,
+        })
+    }
+
     fn render_bubbles(&self) -> Result<(), JsValue> {
         let state_ref = self.state.borrow();
-        let children = self.container.children();
+        let mut renderer = self.renderer.borrow_mut();
+
+        for i in 0..state_ref.expressions.len() {
+            if let Some(view) = Self::bubble_view(&state_ref, i) {
+                renderer.cell_changed(i, &view)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Attaches one click handler per bubble, selecting it.
+    fn attach_bubble_handlers(&self) -> Result<(), JsValue> {
+        let Some(container) = &self.container else {
+            return Ok(());
+        };
+        let children = container.children();
 
         for i in 0..children.length() {
             if let Some(bubble) = children.item(i) {
-                let bubble: Element = bubble.dyn_into::<Element>()?;
-
-                if let Some(expr) = state_ref.expressions.get(i as usize) {
-                    bubble.set_text_content(Some(&expr.text));
-
-                    let class = if state_ref.selected_indices.contains(&(i as usize)) {
-                        "bubble selected"
-                    } else {
-                        "bubble"
-                    };
-                    bubble.set_attribute("class", class)?;
-
-                    let state = self.state.clone();
-                    let bubble_ref = bubble.clone();
-                    let i = i as usize;
-                    let handler = Closure::wrap(Box::new(move |_event: Event| {
-                        let mut state = state.borrow_mut();
-                        if state.toggle_selection(i) {
-                            let is_selected = state.selected_indices.contains(&i);
-                            let class = if is_selected {
-                                "bubble selected"
-                            } else {
-                                "bubble"
-                            };
-                            bubble_ref.set_attribute("class", class).unwrap();
-
-                            if state.selected_indices.len() == 3 {
-                                let round_success = state.check_current_round();
-                                state.update_score(round_success);
-                                state.start_round();
-                            }
-                        }
-                    }) as Box<dyn FnMut(_)>);
-
-                    bubble.add_event_listener_with_callback(
-                        "click",
-                        handler.as_ref().unchecked_ref(),
-                    )?;
-                    handler.forget();
-                }
+                let game = self.clone();
+                let i = i as usize;
+                let handler = Closure::wrap(Box::new(move |_event: Event| {
+                    game.select(i).unwrap();
+                }) as Box<dyn FnMut(_)>);
+
+                bubble
+                    .add_event_listener_with_callback("click", handler.as_ref().unchecked_ref())?;
+                handler.forget();
             }
         }
         Ok(())
@@ -109,9 +124,9 @@ impl Numeracy {
     fn update_stats(&self) -> Result<(), JsValue> {
         let state = self.state.borrow();
 
-        if let Some(level_elem) = self.document.get_element_by_id("level") {
-            level_elem.set_text_content(Some(&state.level.number.to_string()));
-        }
+        self.renderer
+            .borrow_mut()
+            .stats_changed(&[("level", state.level.number.to_string())])?;
 
         // Removed score element update
         Ok(())
@@ -120,34 +135,24 @@ impl Numeracy {
     fn update_timer(&self) -> Result<(), JsValue> {
         let state = self.state.borrow();
 
-        if let Some(timer_elem) = self.document.get_element_by_id("timer") {
-            if state.is_visible {
-                if let Some(remaining) = state.get_round_time_remaining() {
-                    let seconds = (remaining / 1000.0) as u32;
-                    let text = format!("{}:{:02}", seconds / 60, seconds % 60);
-                    timer_elem.set_text_content(Some(&text));
-                }
-            } else {
-                timer_elem.set_text_content(Some("Paused"));
+        if state.is_visible {
+            if let Some(remaining) = state.get_round_time_remaining() {
+                let seconds = (remaining / 1000.0) as u32;
+                let text = format!("{}:{:02}", seconds / 60, seconds % 60);
+                self.renderer.borrow_mut().timer_changed(&text)?;
             }
+        } else {
+            self.renderer.borrow_mut().timer_changed("Paused")?;
         }
         Ok(())
     }
 
     fn start_timer(&self) -> Result<(), JsValue> {
         let window = web_sys::window().unwrap();
-        let state = self.state.clone();
-        let document = self.document.clone();
-        let container = self.container.clone();
+        let game = self.clone();
 
         let closure = Closure::wrap(Box::new(move || {
-            let this = Numeracy {
-                state: state.clone(),
-                document: document.clone(),
-                container: container.clone(),
-            };
-            this.update_timer().unwrap();
-            this.check_time_limits().unwrap();
+            game.tick().unwrap();
         }) as Box<dyn FnMut()>);
 
         window.set_interval_with_callback_and_timeout_and_arguments_0(
@@ -162,7 +167,7 @@ impl Numeracy {
     fn check_time_limits(&self) -> Result<(), JsValue> {
         {
             let mut state = self.state.borrow_mut();
-            
+
             // Only check time limits if the tab is visible
             if state.is_visible {
                 if let Some(remaining) = state.get_round_time_remaining() {
@@ -181,10 +186,194 @@ impl Numeracy {
 
     #[wasm_bindgen]
     pub fn start(&self) -> Result<(), JsValue> {
+        self.begin()?;
+        self.attach_bubble_handlers()?;
+        self.start_timer()?;
+        Ok(())
+    }
+}
+
+impl Numeracy {
+    /// A game off the page: it draws through `renderer`, e.g. a
+    /// `RecordingRenderer` to assert on in tests, keeps its level in
+    /// `storage`, times rounds by `clock` and draws its expressions from
+    /// `seed`. `begin` starts it; no click handlers or timers are attached.
+    pub fn headless(
+        renderer: SharedRenderer,
+        storage: SharedStorage,
+        clock: Clock,
+        seed: u32,
+    ) -> Numeracy {
+        let mut rng = Rng::new(seed);
+        let state = GameState::new(storage, clock, Box::new(move || rng.next_f64()));
+        Numeracy {
+            state: Rc::new(RefCell::new(state)),
+            container: None,
+            renderer,
+        }
+    }
+
+    /// Starts the level and draws its first round.
+    pub fn begin(&self) -> Result<(), JsValue> {
         self.state.borrow_mut().start_level();
         self.render_bubbles()?;
-        self.update_stats()?;
-        self.start_timer()?;
+        self.update_stats()
+    }
+
+    /// Toggles bubble `index`'s selection, scoring the round once three
+    /// are picked.
+    pub fn select(&self, index: usize) -> Result<(), JsValue> {
+        let mut state = self.state.borrow_mut();
+        if state.toggle_selection(index) {
+            if let Some(view) = Self::bubble_view(&state, index) {
+                self.renderer.borrow_mut().cell_changed(index, &view)?;
+            }
+
+            if state.selected_indices.len() == 3 {
+                let round_success = state.check_current_round();
+                state.update_score(round_success);
+                state.start_round();
+            }
+        }
         Ok(())
     }
+
+    /// What the timer does each second: shows the time left in the round
+    /// and ends the round if it has run out.
+    pub fn tick(&self) -> Result<(), JsValue> {
+        self.update_timer()?;
+        self.check_time_limits()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::{
+        renderer::RecordingRenderer,
+        storage::{MemoryStorage, Storage},
+    };
+    use std::cell::Cell;
+
+    struct Harness {
+        game: Numeracy,
+        renderer: Rc<RefCell<RecordingRenderer>>,
+        storage: Rc<RefCell<MemoryStorage>>,
+        time: Rc<Cell<f64>>,
+    }
+
+    fn headless(level: u32) -> Harness {
+        let renderer = Rc::new(RefCell::new(RecordingRenderer::default()));
+        let storage = Rc::new(RefCell::new(MemoryStorage::default()));
+        storage
+            .borrow_mut()
+            .set_item("numeracy_level", &level.to_string())
+            .unwrap();
+        let time = Rc::new(Cell::new(0.0));
+        let clock = {
+            let time = time.clone();
+            Rc::new(move || time.get())
+        };
+        let game = Numeracy::headless(renderer.clone(), storage.clone(), clock, 1);
+        game.begin().unwrap();
+        Harness {
+            game,
+            renderer,
+            storage,
+            time,
+        }
+    }
+
+    /// Bubble indices from the smallest value to the largest.
+    fn ascending(game: &Numeracy) -> Vec<usize> {
+        let state = game.state.borrow();
+        let mut order: Vec<usize> = (0..state.expressions.len()).collect();
+        order.sort_by(|&a, &b| {
+            state.expressions[a]
+                .value
+                .total_cmp(&state.expressions[b].value)
+        });
+        order
+    }
+
+    #[test]
+    fn a_headless_game_draws_its_first_round() {
+        let Harness { game, renderer, .. } = headless(1);
+        let renderer = renderer.borrow();
+        let state = game.state.borrow();
+
+        assert_eq!(renderer.cells.len(), 3);
+        for (cell, expression) in renderer.cells.iter().zip(&state.expressions) {
+            assert_eq!(cell.text, expression.text);
+            assert!(cell.classes.is_empty());
+        }
+        assert_eq!(renderer.stats["level"], "1");
+    }
+
+    #[test]
+    fn the_same_seed_draws_the_same_expressions() {
+        let first = headless(5);
+        let second = headless(5);
+        assert_eq!(
+            first.game.state.borrow().expressions,
+            second.game.state.borrow().expressions
+        );
+    }
+
+    #[test]
+    fn selecting_a_bubble_marks_it_and_selecting_again_clears_it() {
+        let Harness { game, renderer, .. } = headless(1);
+
+        game.select(1).unwrap();
+        assert_eq!(renderer.borrow().cells[1].classes, ["selected"]);
+        game.select(1).unwrap();
+        assert!(renderer.borrow().cells[1].classes.is_empty());
+    }
+
+    #[test]
+    fn a_quick_correct_round_jumps_levels_and_saves() {
+        let Harness {
+            game,
+            renderer,
+            storage,
+            time,
+        } = headless(1);
+
+        time.set(1000.0);
+        for index in ascending(&game) {
+            game.select(index).unwrap();
+        }
+        game.tick().unwrap();
+
+        // 14 seconds left is worth two levels
+        assert_eq!(renderer.borrow().stats["level"], "3");
+        assert_eq!(
+            storage.borrow().get_item("numeracy_level").as_deref(),
+            Some("3")
+        );
+        assert!(game.state.borrow().selected_indices.is_empty());
+    }
+
+    #[test]
+    fn a_round_that_runs_out_drops_a_level() {
+        let Harness {
+            game,
+            renderer,
+            storage,
+            time,
+        } = headless(4);
+
+        time.set(14_500.0);
+        game.tick().unwrap();
+        assert_eq!(renderer.borrow().timer, "0:00");
+        assert_eq!(renderer.borrow().stats["level"], "4");
+
+        time.set(15_000.0);
+        game.tick().unwrap();
+        assert_eq!(renderer.borrow().stats["level"], "3");
+        assert_eq!(
+            storage.borrow().get_item("numeracy_level").as_deref(),
+            Some("3")
+        );
+    }
 }
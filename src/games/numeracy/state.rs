@@ -1,7 +1,7 @@
 use super::{Expression, Level};
-use web_sys::{Performance, Storage};
+use crate::games::{clock::Clock, storage::SharedStorage};
+use web_sys::console;
 
-#[derive(Debug)]
 pub struct GameState {
     pub level: Level,
     pub expressions: Vec<Expression>,
@@ -10,23 +10,22 @@ pub struct GameState {
     pub level_start: Option<f64>,
     pub completed_rounds: u32,
     pub is_visible: bool,
-    performance: Performance,
-    storage: Storage,
+    clock: Clock,
+    storage: SharedStorage,
+    // Floats in `[0, 1)` the expressions are drawn from
+    random: Box<dyn FnMut() -> f64>,
 }
 
 impl GameState {
-    pub fn new() -> Self {
-        let window = web_sys::window().unwrap();
-        let storage = window.local_storage().unwrap().unwrap();
+    pub fn new(storage: SharedStorage, clock: Clock, mut random: Box<dyn FnMut() -> f64>) -> Self {
         let level_number = storage
+            .borrow()
             .get_item("numeracy_level")
-            .unwrap()
             .and_then(|s| s.parse().ok())
             .unwrap_or(1);
 
         let level = Level::new(level_number);
-        let expressions = level.generate_expressions();
-        let performance = window.performance().unwrap();
+        let expressions = level.generate_expressions(&mut random);
 
         Self {
             level,
@@ -36,20 +35,21 @@ impl GameState {
             level_start: None,
             completed_rounds: 0,
             is_visible: true,
-            performance,
+            clock,
             storage,
+            random,
         }
     }
 
     pub fn start_level(&mut self) {
-        self.level_start = Some(self.performance.now());
+        self.level_start = Some((self.clock)());
         self.start_round();
     }
 
     pub fn start_round(&mut self) {
-        self.expressions = self.level.generate_expressions();
+        self.expressions = self.level.generate_expressions(&mut self.random);
         self.selected_indices.clear();
-        self.round_start = Some(self.performance.now());
+        self.round_start = Some((self.clock)());
     }
 
     pub fn toggle_selection(&mut self, index: usize) -> bool {
@@ -80,7 +80,7 @@ impl GameState {
 
     pub fn get_round_time_remaining(&self) -> Option<f64> {
         self.round_start.map(|start| {
-            let elapsed = self.performance.now() - start;
+            let elapsed = (self.clock)() - start;
             if elapsed >= 15000.0 {
                 0.0
             } else {
@@ -103,20 +103,26 @@ impl GameState {
             };
 
             if level_jump > 0 {
-                let new_level = self.level.number + level_jump;
-                self.level = Level::new(new_level);
-                self.storage
-                    .set_item("numeracy_level", &new_level.to_string())
-                    .unwrap();
+                self.level = Level::new(self.level.number + level_jump);
+                self.save_level();
             }
         } else if self.level.number > 1 {
-            let new_level = self.level.number - 1;
-            self.level = Level::new(new_level);
-            self.storage
-                .set_item("numeracy_level", &new_level.to_string())
-                .unwrap();
+            self.level = Level::new(self.level.number - 1);
+            self.save_level();
         }
 
         self.completed_rounds += 1;
     }
+
+    /// Remembers the level for the next visit. A failed write, e.g. over
+    /// quota, only costs that.
+    fn save_level(&self) {
+        let saved = self
+            .storage
+            .borrow_mut()
+            .set_item("numeracy_level", &self.level.number.to_string());
+        if let Err(e) = saved {
+            console::error_2(&"Failed to save level:".into(), &e);
+        }
+    }
 }
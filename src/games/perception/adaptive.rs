@@ -0,0 +1,245 @@
+//! Adaptive difficulty. A rating, in levels, steps up after each completed
+//! maze and down after each failed one, weighted so that it settles where
+//! the player succeeds at the target rate: a success moves it up by
+//! `1 - target` steps and a failure down by `target`. Completions that were
+//! quick, close to par and free of wall hits step up further than scrappy
+//! ones. The time limit follows the player's recent pace per move of the
+//! optimal route, so a bigger maze brings more time and a slower player
+//! gets more too.
+
+use super::{difficulty::Level, Perception};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+// Levels the rating moves per outcome, before weighting
+const STEP: f64 = 1.0;
+// Outcomes the recent success rate is taken over
+const WINDOW: usize = 10;
+// Share of the time limit a player going at their recent pace should need
+const TIME_USE: f64 = 0.6;
+// Weight of the latest completion in the pace
+const PACE_WEIGHT: f64 = 0.4;
+// Pace is slowed by this much after a timeout
+const TIMEOUT_SLOWDOWN: f64 = 1.25;
+// Bounds on the time limits the controller sets, in seconds
+const MIN_TIME_LIMIT: f64 = 30.0;
+const MAX_TIME_LIMIT: f64 = 900.0;
+
+/// Whether levels adapt to the player, and the success rate they aim for.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(super) struct Adaptive {
+    pub(super) enabled: bool,
+    // Share of levels, from 0.5 to 0.95, the player should complete
+    pub(super) target: f64,
+}
+
+impl Default for Adaptive {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target: 0.75,
+        }
+    }
+}
+
+/// How an attempt at a level ended.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum Outcome {
+    Completed {
+        // Seconds from the maze appearing to the exit, and left on the clock
+        seconds: f64,
+        time_left: i32,
+        par: usize,
+        moves: usize,
+        wall_hits: usize,
+    },
+    TimedOut,
+    OutOfLives,
+}
+
+/// What the controller has learnt of the player, saved with the game.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(super) struct Controller {
+    // Level the player is at, with fractions of a step between levels
+    rating: Option<f64>,
+    // Seconds per move of the optimal route on recent completions
+    pace: Option<f64>,
+    // Latest outcomes, true for a completion
+    recent: VecDeque<bool>,
+}
+
+impl Controller {
+    /// Takes in how an attempt at level `level` went and returns the level
+    /// to play next.
+    pub(super) fn record(&mut self, level: usize, outcome: Outcome, target: f64) -> usize {
+        // The level may have been changed from outside, e.g. by a reset
+        let mut rating = self
+            .rating
+            .filter(|rating| rating.round() as usize == level)
+            .unwrap_or(level as f64);
+
+        match outcome {
+            Outcome::Completed {
+                seconds,
+                time_left,
+                par,
+                moves,
+                wall_hits,
+            } => {
+                // Each from 0 to 1: time to spare, par against moves, no walls hit
+                let left = f64::from(time_left.max(0));
+                let quick = left / (seconds + left).max(1.0);
+                let efficient = (par as f64 / moves.max(1) as f64).min(1.0);
+                let clean = 1.0 / (1.0 + wall_hits as f64);
+                let quality = (quick + efficient + clean) / 3.0;
+                rating += STEP * (1.0 - target) * (0.5 + quality);
+
+                let pace = seconds / par.max(1) as f64;
+                self.pace = Some(
+                    self.pace
+                        .map_or(pace, |previous| previous + PACE_WEIGHT * (pace - previous)),
+                );
+            }
+            Outcome::TimedOut => {
+                rating -= STEP * target;
+                self.pace = self.pace.map(|pace| pace * TIMEOUT_SLOWDOWN);
+            }
+            Outcome::OutOfLives => rating -= STEP * target,
+        }
+
+        let rating = rating.max(1.0);
+        self.rating = Some(rating);
+        self.recent
+            .push_back(matches!(outcome, Outcome::Completed { .. }));
+        if self.recent.len() > WINDOW {
+            self.recent.pop_front();
+        }
+        rating.round() as usize
+    }
+
+    /// Time limit for a maze whose optimal route takes `par` moves; the
+    /// curve's `fallback` until a completion has set the pace.
+    pub(super) fn time_limit(&self, par: usize, fallback: i32) -> i32 {
+        match self.pace {
+            Some(pace) => (pace * par as f64 / TIME_USE)
+                .clamp(MIN_TIME_LIMIT, MAX_TIME_LIMIT)
+                .ceil() as i32,
+            None => fallback,
+        }
+    }
+
+    /// Share of the latest levels completed, if any have been played.
+    pub(super) fn success_rate(&self) -> Option<f64> {
+        let completed = self.recent.iter().filter(|&&completed| completed).count();
+        (!self.recent.is_empty()).then(|| completed as f64 / self.recent.len() as f64)
+    }
+}
+
+impl Perception {
+    /// The level to play after `outcome`: the controller's choice when
+    /// levels adapt, else the next one after a completion and the same one
+    /// again after a failure.
+    pub(super) fn level_after(&mut self, outcome: Outcome) -> usize {
+        let adaptive = self.settings.adaptive;
+        if adaptive.enabled {
+            return self
+                .adaptation
+                .record(self.board.level, outcome, adaptive.target);
+        }
+        match outcome {
+            Outcome::Completed { .. } => self.board.level + 1,
+            _ => self.board.level,
+        }
+    }
+
+    /// Seconds on the clock for the maze now on the board, set up as `level`.
+    pub(super) fn time_limit_for(&self, level: &Level) -> i32 {
+        if self.settings.adaptive.enabled {
+            self.adaptation
+                .time_limit(self.board.maze.par, level.time_limit)
+        } else {
+            level.time_limit
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A completion of middling quality: no time to spare, at par, one wall
+    /// hit. It steps the rating by exactly `1 - target`.
+    fn middling(seconds: f64, par: usize) -> Outcome {
+        Outcome::Completed {
+            seconds,
+            time_left: 0,
+            par,
+            moves: par,
+            wall_hits: 1,
+        }
+    }
+
+    #[test]
+    fn a_success_steps_up_by_one_less_the_target() {
+        let mut controller = Controller::default();
+        assert_eq!(controller.record(5, middling(60.0, 20), 0.75), 5);
+        assert_eq!(controller.rating, Some(5.25));
+        assert_eq!(controller.record(5, middling(60.0, 20), 0.75), 6);
+        assert_eq!(controller.rating, Some(5.5));
+    }
+
+    #[test]
+    fn a_failure_steps_down_by_the_target() {
+        let mut controller = Controller::default();
+        assert_eq!(controller.record(5, Outcome::TimedOut, 0.75), 4);
+        assert_eq!(controller.rating, Some(4.25));
+        assert_eq!(controller.record(4, Outcome::OutOfLives, 0.75), 4);
+        assert_eq!(controller.rating, Some(3.5));
+    }
+
+    #[test]
+    fn the_rating_stays_at_level_one_or_above() {
+        let mut controller = Controller::default();
+        for _ in 0..5 {
+            assert_eq!(controller.record(1, Outcome::OutOfLives, 0.9), 1);
+        }
+        assert_eq!(controller.rating, Some(1.0));
+    }
+
+    #[test]
+    fn time_limits_follow_the_pace_within_bounds() {
+        let mut controller = Controller::default();
+        assert_eq!(controller.time_limit(10, 120), 120);
+
+        controller.record(3, middling(30.0, 10), 0.75);
+        assert_eq!(controller.time_limit(10, 120), 50);
+
+        let mut quick = Controller::default();
+        quick.record(3, middling(1.0, 100), 0.75);
+        assert_eq!(quick.time_limit(10, 120), MIN_TIME_LIMIT as i32);
+
+        let mut slow = Controller::default();
+        slow.record(3, middling(10_000.0, 1), 0.75);
+        assert_eq!(slow.time_limit(10, 120), MAX_TIME_LIMIT as i32);
+    }
+
+    #[test]
+    fn the_success_rate_covers_the_last_ten_outcomes() {
+        let mut controller = Controller::default();
+        assert_eq!(controller.success_rate(), None);
+
+        controller.record(3, Outcome::TimedOut, 0.75);
+        controller.record(3, Outcome::TimedOut, 0.75);
+        for _ in 0..WINDOW - 1 {
+            controller.record(3, middling(60.0, 20), 0.75);
+        }
+        assert_eq!(controller.recent.len(), WINDOW);
+        assert_eq!(controller.success_rate(), Some(0.9));
+
+        controller.record(3, middling(60.0, 20), 0.75);
+        assert_eq!(controller.recent.len(), WINDOW);
+        assert_eq!(controller.success_rate(), Some(1.0));
+    }
+}
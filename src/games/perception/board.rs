@@ -0,0 +1,428 @@
+use super::{
+    difficulty::Level,
+    maze::{Maze, MazeOptions},
+    solver,
+};
+use crate::games::rng::next_seed;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Outcome of a single move attempt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) enum MoveResult {
+    /// Target is not next to the player; nothing changed.
+    NotAdjacent,
+    /// Target is a door whose key has not been collected yet.
+    DoorLocked,
+    /// A wall was in the way, and the penalty for it was applied.
+    WallHit,
+    /// A wall was in the way and it cost the last life.
+    OutOfLives,
+    Moved,
+    KeyCollected,
+    /// The exit was reached; `next_level` sets up the level after.
+    LevelComplete,
+}
+
+impl MoveResult {
+    /// Whether the attempt changed the board and needs a redraw.
+    pub(super) fn changed(self) -> bool {
+        !matches!(self, Self::NotAdjacent | Self::DoorLocked)
+    }
+}
+
+// Most seconds a wall hit can cost, and most lives a maze can be played with
+pub(super) const MAX_TIME_PENALTY: i32 = 300;
+pub(super) const MAX_LIVES: usize = 9;
+
+/// What walking into a wall costs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) enum Penalty {
+    /// Back to the start, dropping every key.
+    #[default]
+    Restart,
+    /// Back to where the last key was picked up, keeping the keys.
+    Checkpoint,
+    /// This many seconds off the clock; the player stays put.
+    Time(i32),
+    /// One of this many lives a maze; the player stays put. Losing the
+    /// last one fails the level.
+    Lives(usize),
+}
+
+impl Penalty {
+    /// The same penalty costing 1 to `MAX_TIME_PENALTY` seconds or 1 to
+    /// `MAX_LIVES` lives.
+    pub(super) fn clamped(self) -> Self {
+        match self {
+            Self::Time(seconds) => Self::Time(seconds.clamp(1, MAX_TIME_PENALTY)),
+            Self::Lives(lives) => Self::Lives(lives.clamp(1, MAX_LIVES)),
+            other => other,
+        }
+    }
+}
+
+/// DOM-free game state: the maze plus the player's progress through it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(super) struct Board {
+    pub(super) level: usize,
+    #[serde(default)]
+    pub(super) moves: usize,
+    // Hints used on the current level
+    #[serde(default)]
+    pub(super) hints: usize,
+    // Options for every maze generated from now on
+    #[serde(default)]
+    pub(super) options: MazeOptions,
+    // Cost of a wall hit, carried over from one maze to the next
+    #[serde(default)]
+    pub(super) penalty: Penalty,
+    // Lives lost on this maze, under `Penalty::Lives`
+    #[serde(default)]
+    pub(super) lives_lost: usize,
+    pub(super) maze: Maze,
+    pub(super) current_position: (usize, usize),
+    pub(super) visited: HashSet<(usize, usize)>,
+    // Keys collected on this attempt, in pickup order
+    #[serde(default)]
+    pub(super) inventory: Vec<usize>,
+    // Par as a percentage of the moves taken on the last completed level
+    #[serde(default)]
+    pub(super) last_efficiency: Option<usize>,
+}
+
+impl Board {
+    pub(super) fn new(maze: Maze, level: usize) -> Self {
+        let mut visited = HashSet::new();
+        visited.insert(maze.start_position);
+        Self {
+            level,
+            moves: 0,
+            hints: 0,
+            options: maze.options,
+            penalty: Penalty::default(),
+            lives_lost: 0,
+            current_position: maze.start_position,
+            maze,
+            visited,
+            inventory: Vec::new(),
+            last_efficiency: None,
+        }
+    }
+
+    /// Checks a restored board: a real level, the player and their keys
+    /// inside the maze, and a penalty that can be paid.
+    pub(super) fn validate(&self) -> Result<(), &'static str> {
+        self.options.validate()?;
+        self.maze.validate()?;
+        if self.level == 0 {
+            return Err("level is 0");
+        }
+        if !self.maze.contains(self.current_position)
+            || !self.visited.iter().all(|&cell| self.maze.contains(cell))
+        {
+            return Err("player has been outside the maze");
+        }
+        if self
+            .inventory
+            .iter()
+            .any(|&key| key >= self.maze.keys.len())
+        {
+            return Err("inventory holds a key the maze lacks");
+        }
+        if self.penalty != self.penalty.clamped() {
+            return Err("wall penalty is impossible");
+        }
+        Ok(())
+    }
+
+    pub(super) fn try_move(&mut self, x: usize, y: usize) -> MoveResult {
+        if !self.maze.is_adjacent(self.current_position, (x, y)) {
+            return MoveResult::NotAdjacent;
+        }
+
+        // Block access to doors whose key has not been collected
+        let door = self.maze.door_at((x, y));
+        if door.is_some_and(|door| !self.inventory.contains(&door)) {
+            return MoveResult::DoorLocked;
+        }
+
+        if self.maze.has_wall(self.current_position, (x, y)) {
+            return self.hit_wall();
+        }
+
+        self.moves += 1;
+        self.current_position = (x, y);
+        self.visited.insert((x, y));
+
+        if let Some(key) = self.maze.key_at((x, y)) {
+            let in_order = !self.maze.options.ordered_keys || key == self.inventory.len();
+            if !self.inventory.contains(&key) && in_order {
+                self.inventory.push(key);
+                return MoveResult::KeyCollected;
+            }
+        }
+
+        if door == Some(self.maze.exit()) {
+            return MoveResult::LevelComplete;
+        }
+
+        MoveResult::Moved
+    }
+
+    /// Records a hint and returns the next cell on the optimal route.
+    pub(super) fn hint(&mut self) -> Option<(usize, usize)> {
+        let step = solver::next_step(&self.maze, self.current_position, &self.inventory)?;
+        self.hints += 1;
+        Some(step)
+    }
+
+    /// Applies the penalty for walking into a wall. Time is the caller's to
+    /// take.
+    fn hit_wall(&mut self) -> MoveResult {
+        match self.penalty {
+            Penalty::Restart => self.reset_position(),
+            Penalty::Checkpoint => self.current_position = self.checkpoint(),
+            Penalty::Time(_) => {}
+            Penalty::Lives(lives) => {
+                self.lives_lost += 1;
+                if self.lives_lost >= lives {
+                    return MoveResult::OutOfLives;
+                }
+            }
+        }
+        MoveResult::WallHit
+    }
+
+    /// Where the last key held was picked up, or the start without one.
+    fn checkpoint(&self) -> (usize, usize) {
+        self.inventory
+            .last()
+            .map_or(self.maze.start_position, |&key| self.maze.keys[key])
+    }
+
+    /// Sends the player back to the start and drops every key.
+    pub(super) fn reset_position(&mut self) {
+        self.current_position = self.maze.start_position;
+        self.visited.clear();
+        self.visited.insert(self.maze.start_position);
+        self.inventory.clear();
+    }
+
+    /// A fresh maze set up as `level` says.
+    fn next_maze(&self, level: &Level) -> Maze {
+        let (width, height) = level.options.dimensions(level.size);
+        Maze::generate(width, height, next_seed(self.maze.seed), level.options)
+    }
+
+    /// Moves on from the level just completed to level `number`, set up as
+    /// `level`.
+    pub(super) fn next_level(&mut self, number: usize, level: &Level) {
+        let efficiency = (self.maze.par * 100 / self.moves.max(1)).min(100);
+        *self = Self {
+            options: self.options,
+            penalty: level.penalty,
+            last_efficiency: Some(efficiency),
+            ..Self::new(self.next_maze(level), number)
+        };
+    }
+
+    /// Replaces the maze with a fresh one for the same level, now set up as
+    /// `level`.
+    pub(super) fn regenerate(&mut self, level: &Level) {
+        self.maze = self.next_maze(level);
+        self.penalty = level.penalty;
+        self.lives_lost = 0;
+        self.reset_position();
+    }
+
+    /// Starts over at level `number`, set up as `level`.
+    pub(super) fn restart_at_level(&mut self, number: usize, level: &Level) {
+        *self = Self {
+            options: self.options,
+            penalty: level.penalty,
+            ..Self::new(self.next_maze(level), number)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::perception::{difficulty::Curve, maze::tests::sample_mazes};
+
+    /// A board on a maze with several keys, keys picked up in any order.
+    fn board() -> Board {
+        let maze = sample_mazes()
+            .find(|maze| maze.keys.len() == 3 && !maze.options.ordered_keys)
+            .unwrap();
+        Board::new(maze, 1)
+    }
+
+    /// A cell an open passage leads into `to` from, that holds no item.
+    fn open_side(board: &Board, to: (usize, usize)) -> (usize, usize) {
+        let grid = board.maze.grid();
+        grid.open_neighbours(grid.cell(to))
+            .into_iter()
+            .map(|cell| grid.position(cell))
+            .find(|&cell| board.maze.key_at(cell).is_none() && board.maze.door_at(cell).is_none())
+            .unwrap()
+    }
+
+    /// Two neighbouring cells with a wall between them, neither a door.
+    fn walled_pair(board: &Board) -> ((usize, usize), (usize, usize)) {
+        let grid = board.maze.grid();
+        (0..grid.cell_count())
+            .flat_map(|cell| {
+                grid.neighbours(cell)
+                    .into_iter()
+                    .map(move |(neighbour, _, _)| (cell, neighbour))
+            })
+            .map(|(from, to)| (grid.position(from), grid.position(to)))
+            .find(|&(from, to)| board.maze.has_wall(from, to) && board.maze.door_at(to).is_none())
+            .unwrap()
+    }
+
+    #[test]
+    fn walking_the_solution_completes_every_sample_maze() {
+        for maze in sample_mazes() {
+            let route = solver::solve(&maze).unwrap();
+            let mut board = Board::new(maze, 1);
+            let (last, steps) = route[1..].split_last().unwrap();
+            for &(x, y) in steps {
+                let result = board.try_move(x, y);
+                assert!(
+                    matches!(result, MoveResult::Moved | MoveResult::KeyCollected),
+                    "{:?} on the way to the exit",
+                    result
+                );
+            }
+            assert_eq!(board.try_move(last.0, last.1), MoveResult::LevelComplete);
+            assert_eq!(board.inventory.len(), board.maze.keys.len());
+            assert_eq!(board.moves, board.maze.par);
+        }
+    }
+
+    #[test]
+    fn doors_stay_locked_without_their_key() {
+        let mut board = board();
+        let door = board.maze.doors[1];
+        let from = open_side(&board, door);
+        board.current_position = from;
+        board.inventory = vec![0, 2];
+
+        assert_eq!(board.try_move(door.0, door.1), MoveResult::DoorLocked);
+        assert_eq!(board.current_position, from);
+        assert_eq!(board.moves, 0);
+
+        board.inventory.push(1);
+        assert_eq!(board.try_move(door.0, door.1), MoveResult::Moved);
+    }
+
+    #[test]
+    fn stepping_onto_a_key_collects_it() {
+        let mut board = board();
+        let key = board.maze.keys[2];
+        board.current_position = open_side(&board, key);
+
+        assert_eq!(board.try_move(key.0, key.1), MoveResult::KeyCollected);
+        assert_eq!(board.inventory, [2]);
+        assert_eq!(board.current_position, key);
+    }
+
+    #[test]
+    fn ordered_keys_only_collect_in_order() {
+        let mut board = board();
+        board.maze.options.ordered_keys = true;
+        let key = board.maze.keys[1];
+        board.current_position = open_side(&board, key);
+
+        assert_eq!(board.try_move(key.0, key.1), MoveResult::Moved);
+        assert!(board.inventory.is_empty());
+    }
+
+    #[test]
+    fn cells_that_are_not_neighbours_are_not_moves() {
+        let mut board = board();
+        let (x, y) = board.maze.doors[2];
+        assert_eq!(board.try_move(x, y), MoveResult::NotAdjacent);
+        assert_eq!(board.current_position, board.maze.start_position);
+    }
+
+    #[test]
+    fn walls_apply_the_penalty() {
+        let mut board = board();
+        let (from, to) = walled_pair(&board);
+        board.inventory = vec![0];
+
+        board.current_position = from;
+        assert_eq!(board.try_move(to.0, to.1), MoveResult::WallHit);
+        assert_eq!(board.current_position, board.maze.start_position);
+        assert!(board.inventory.is_empty());
+
+        board.penalty = Penalty::Checkpoint;
+        board.inventory = vec![1, 0];
+        board.current_position = from;
+        assert_eq!(board.try_move(to.0, to.1), MoveResult::WallHit);
+        assert_eq!(board.current_position, board.maze.keys[0]);
+        assert_eq!(board.inventory, vec![1, 0]);
+
+        board.penalty = Penalty::Time(10);
+        board.current_position = from;
+        assert_eq!(board.try_move(to.0, to.1), MoveResult::WallHit);
+        assert_eq!(board.current_position, from);
+
+        board.penalty = Penalty::Lives(3);
+        assert_eq!(board.try_move(to.0, to.1), MoveResult::WallHit);
+        assert_eq!(board.current_position, from);
+        assert_eq!(board.lives_lost, 1);
+        assert_eq!(board.moves, 0);
+    }
+
+    #[test]
+    fn the_last_life_is_lost_on_a_wall() {
+        let mut board = board();
+        let (from, to) = walled_pair(&board);
+        board.penalty = Penalty::Lives(2);
+        board.current_position = from;
+
+        assert_eq!(board.try_move(to.0, to.1), MoveResult::WallHit);
+        assert_eq!(board.try_move(to.0, to.1), MoveResult::OutOfLives);
+        assert_eq!(board.lives_lost, 2);
+    }
+
+    #[test]
+    fn penalties_are_clamped_to_what_can_be_paid() {
+        assert_eq!(Penalty::Time(0).clamped(), Penalty::Time(1));
+        assert_eq!(Penalty::Time(-5).clamped(), Penalty::Time(1));
+        assert_eq!(
+            Penalty::Time(1000).clamped(),
+            Penalty::Time(MAX_TIME_PENALTY)
+        );
+        assert_eq!(Penalty::Lives(0).clamped(), Penalty::Lives(1));
+        assert_eq!(Penalty::Lives(50).clamped(), Penalty::Lives(MAX_LIVES));
+        assert_eq!(Penalty::Checkpoint.clamped(), Penalty::Checkpoint);
+    }
+
+    #[test]
+    fn each_level_is_seeded_from_the_one_before() {
+        let curve = Curve::default();
+        let options = MazeOptions::default();
+        let first = curve.level(1, options, Penalty::default());
+        let (width, height) = first.options.dimensions(first.size);
+        let mut board = Board::new(Maze::generate(width, height, 42, first.options), 1);
+
+        let mut seed = 42;
+        for number in 2..6 {
+            let level = curve.level(number, options, Penalty::default());
+            board.next_level(number, &level);
+            seed = next_seed(seed);
+
+            let (width, height) = level.options.dimensions(level.size);
+            assert_eq!(
+                board.maze,
+                Maze::generate(width, height, seed, level.options)
+            );
+        }
+    }
+}
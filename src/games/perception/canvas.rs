@@ -0,0 +1,352 @@
+use super::{drawing::PAIR_COLOURS, maze::Maze, topology::Topology, Perception};
+use crate::games::renderer::{CellView, DomRenderer, Layout, Renderer};
+use std::{convert::TryFrom, f64::consts::TAU};
+use wasm_bindgen::prelude::*;
+use web_sys::{CanvasRenderingContext2d, Document, HtmlCanvasElement};
+
+// Largest cell, in CSS pixels, matching the DOM renderer's cells
+const MAX_CELL: f64 = 60.0;
+// Widest the canvas grows before cells shrink, in CSS pixels
+const MAX_WIDTH: f64 = 960.0;
+// How long a wall stays lit after being hit, in milliseconds
+const FLASH_DURATION: f64 = 1000.0;
+
+/// CSS pixels per cell width for a board `width` cells wide: as large as DOM
+/// cells, shrinking so wide mazes still fit.
+fn scale_for(width: f64) -> f64 {
+    (MAX_WIDTH / width).min(MAX_CELL)
+}
+
+/// Colours from the page's stylesheet, so the canvas follows its theme.
+struct Palette {
+    background: String,
+    visited: String,
+    border: String,
+    text: String,
+    magma: String,
+}
+
+impl Palette {
+    fn read(canvas: &HtmlCanvasElement) -> Self {
+        let style = web_sys::window().and_then(|w| w.get_computed_style(canvas).ok().flatten());
+        let var = |name: &str, fallback: &str| {
+            style
+                .as_ref()
+                .and_then(|style| style.get_property_value(name).ok())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .unwrap_or_else(|| fallback.to_string())
+        };
+        Self {
+            background: var("--bg-color", "#666666"),
+            visited: var("--bg-2", "#999999"),
+            border: var("--border-color", "#000000"),
+            text: var("--visited-fg", "#491672"),
+            magma: var("--magma-color", "#400000"),
+        }
+    }
+}
+
+/// Whether `point` lies inside the polygon with the given corners.
+fn contains(corners: &[(f64, f64)], (px, py): (f64, f64)) -> bool {
+    let mut inside = false;
+    let mut previous = corners[corners.len() - 1];
+    for &(x, y) in corners {
+        if (y > py) != (previous.1 > py) && px < (previous.0 - x) * (py - y) / (previous.1 - y) + x
+        {
+            inside = !inside;
+        }
+        previous = (x, y);
+    }
+    inside
+}
+
+/// The cell of `maze` holding a point in maze units, if any.
+fn cell_at(maze: &Maze, point: (f64, f64)) -> Option<(usize, usize)> {
+    let topology = maze.options.topology.cells();
+    let (x, y) = topology.near(point);
+    let in_maze = |(x, y): (isize, isize)| {
+        let (x, y) = (usize::try_from(x).ok()?, usize::try_from(y).ok()?);
+        maze.contains((x, y)).then_some((x, y))
+    };
+    if maze.options.topology == Topology::Square {
+        return in_maze((x, y));
+    }
+    (-1..=1)
+        .flat_map(|dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+        .filter_map(in_maze)
+        .find(|&(x, y)| contains(&topology.corners(x, y), point))
+}
+
+fn trace(context: &CanvasRenderingContext2d, corners: &[(f64, f64)]) {
+    context.begin_path();
+    context.move_to(corners[0].0, corners[0].1);
+    for &(x, y) in &corners[1..] {
+        context.line_to(x, y);
+    }
+    context.close_path();
+}
+
+/// Draws the maze on a single canvas, which stays quick however many cells
+/// there are. Stats, the timer and buttons are still page elements.
+pub(super) struct CanvasRenderer {
+    canvas: HtmlCanvasElement,
+    context: CanvasRenderingContext2d,
+    page: DomRenderer,
+    palette: Palette,
+    layout: Option<Layout>,
+    cells: Vec<CellView>,
+    // Cell, wall and start time of a wall lit up after a hit
+    flash: Option<(usize, usize, f64)>,
+}
+
+impl CanvasRenderer {
+    /// None if the page has no usable `#maze-canvas`.
+    pub(super) fn new(document: Document) -> Option<Self> {
+        let canvas = document
+            .get_element_by_id("maze-canvas")?
+            .dyn_into::<HtmlCanvasElement>()
+            .ok()?;
+        let context = canvas
+            .get_context("2d")
+            .ok()??
+            .dyn_into::<CanvasRenderingContext2d>()
+            .ok()?;
+        Some(Self {
+            palette: Palette::read(&canvas),
+            canvas,
+            context,
+            page: DomRenderer::new(document, "maze", "cell"),
+            layout: None,
+            cells: Vec::new(),
+            flash: None,
+        })
+    }
+
+    fn scale(&self) -> f64 {
+        self.layout
+            .as_ref()
+            .map_or(MAX_CELL, |layout| scale_for(layout.extent.0))
+    }
+
+    /// Sets the transform so one unit is `unit` CSS pixels.
+    fn units(&self, unit: f64) -> Result<(), JsValue> {
+        let ratio = web_sys::window().map_or(1.0, |window| window.device_pixel_ratio());
+        self.context
+            .set_transform(ratio * unit, 0.0, 0.0, ratio * unit, 0.0, 0.0)
+    }
+
+    fn draw_cell(&self, index: usize) -> Result<(), JsValue> {
+        let (Some(layout), Some(view)) = (&self.layout, self.cells.get(index)) else {
+            return Ok(());
+        };
+        let context = &self.context;
+        let corners = &layout.cells[index];
+        let has = |class: &str| view.classes.iter().any(|c| c == class);
+        let n = corners.len() as f64;
+        let (cx, cy) = corners
+            .iter()
+            .fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x / n, sy + y / n));
+        let scale = self.scale();
+
+        // Shapes are drawn in maze units
+        self.units(scale)?;
+        trace(context, corners);
+        let visited = has("visited") || has("current");
+        context.set_fill_style_str(if visited {
+            &self.palette.visited
+        } else {
+            &self.palette.background
+        });
+        context.fill();
+        context.set_line_width(1.0 / scale);
+        context.set_stroke_style_str(&self.palette.border);
+        context.stroke();
+
+        if has("ghost") {
+            context.begin_path();
+            context.arc(cx, cy, 0.3, 0.0, TAU)?;
+            context.set_fill_style_str("rgb(165 50 255 / 0.45)");
+            context.fill();
+        }
+        let colour = view
+            .classes
+            .iter()
+            .find_map(|class| class.strip_prefix("colour-")?.parse::<usize>().ok());
+        if let Some(colour) = colour {
+            // Inset so neighbouring cells do not paint over the ring
+            context.save();
+            trace(context, corners);
+            context.clip();
+            context.set_line_width(0.16);
+            context.set_stroke_style_str(PAIR_COLOURS[colour % PAIR_COLOURS.len()]);
+            context.stroke();
+            context.restore();
+        }
+        if has("hint") {
+            trace(context, corners);
+            context.set_line_dash(&js_sys::Array::of2(&0.12.into(), &0.08.into()))?;
+            context.set_line_width(0.08);
+            context.set_stroke_style_str(&self.palette.text);
+            context.stroke();
+            context.set_line_dash(&js_sys::Array::new())?;
+        }
+
+        // Text is drawn in CSS pixels so font sizes stay whole
+        if !view.text.is_empty() {
+            self.units(1.0)?;
+            let font_size = scale * if layout.columns.is_some() { 0.7 } else { 0.45 };
+            context.set_font(&format!("{font_size:.0}px emoji, \"Noto Emoji\""));
+            context.set_text_align("center");
+            context.set_text_baseline("middle");
+            context.set_fill_style_str(&self.palette.text);
+            context.fill_text(&view.text, cx * scale, cy * scale)?;
+        }
+        Ok(())
+    }
+
+    /// Strokes the lit wall over its cell, fading with `elapsed` milliseconds.
+    fn draw_flash(&self, index: usize, wall: usize, elapsed: f64) -> Result<(), JsValue> {
+        let Some(layout) = &self.layout else {
+            return Ok(());
+        };
+        let corners = &layout.cells[index];
+        let (start, end) = (corners[wall], corners[(wall + 1) % corners.len()]);
+        let context = &self.context;
+        self.units(self.scale())?;
+        // Clipped to the cell, like the DOM renderer's border flash
+        context.save();
+        trace(context, corners);
+        context.clip();
+        context.set_global_alpha((1.0 - elapsed / FLASH_DURATION).max(0.0));
+        context.begin_path();
+        context.move_to(start.0, start.1);
+        context.line_to(end.0, end.1);
+        context.set_line_width(0.24);
+        context.set_stroke_style_str(&self.palette.magma);
+        context.stroke();
+        context.restore();
+        Ok(())
+    }
+}
+
+impl Renderer for CanvasRenderer {
+    fn layout_changed(&mut self, layout: &Layout) -> Result<(), JsValue> {
+        if self.layout.as_ref() == Some(layout) {
+            return Ok(());
+        }
+        self.layout = Some(layout.clone());
+        self.cells = vec![CellView::default(); layout.cells.len()];
+        self.flash = None;
+        self.palette = Palette::read(&self.canvas);
+
+        // Size the backing store for sharp lines on high-density screens
+        let scale = self.scale();
+        let ratio = web_sys::window().map_or(1.0, |window| window.device_pixel_ratio());
+        let (css_width, css_height) = (
+            (layout.extent.0 * scale).ceil(),
+            (layout.extent.1 * scale).ceil(),
+        );
+        self.canvas.set_width((css_width * ratio) as u32);
+        self.canvas.set_height((css_height * ratio) as u32);
+        self.canvas.set_attribute(
+            "style",
+            &format!("width: {css_width}px; height: {css_height}px"),
+        )
+    }
+
+    fn cell_changed(&mut self, index: usize, view: &CellView) -> Result<(), JsValue> {
+        if self.cells.get(index) == Some(view) {
+            return Ok(());
+        }
+        if let Some(cell) = self.cells.get_mut(index) {
+            *cell = view.clone();
+        }
+        self.draw_cell(index)
+    }
+
+    fn stats_changed(&mut self, stats: &[(&str, String)]) -> Result<(), JsValue> {
+        self.page.stats_changed(stats)
+    }
+
+    fn timer_changed(&mut self, text: &str) -> Result<(), JsValue> {
+        self.page.timer_changed(text)
+    }
+
+    fn wall_hit(&mut self, index: usize, wall: usize) -> Result<(), JsValue> {
+        let now = web_sys::window()
+            .and_then(|window| window.performance())
+            .map_or(0.0, |performance| performance.now());
+        if let Some((previous, _, _)) = self.flash.replace((index, wall, now)) {
+            self.draw_cell(previous)?;
+        }
+        self.draw_flash(index, wall, 0.0)
+    }
+
+    fn control_shown(&mut self, id: &str, visible: bool) -> Result<(), JsValue> {
+        self.page.control_shown(id, visible)
+    }
+
+    fn tick(&mut self, now: f64) -> Result<(), JsValue> {
+        let Some((index, wall, started)) = self.flash else {
+            return Ok(());
+        };
+        self.draw_cell(index)?;
+        if now - started >= FLASH_DURATION {
+            self.flash = None;
+            return Ok(());
+        }
+        self.draw_flash(index, wall, now - started)
+    }
+}
+
+impl Perception {
+    /// The cell under a point given in CSS pixels from the canvas's corner.
+    pub(super) fn canvas_cell_at(&self, x: f64, y: f64) -> Option<(usize, usize)> {
+        let canvas = self
+            .document
+            .as_ref()?
+            .get_element_by_id("maze-canvas")?
+            .dyn_into::<HtmlCanvasElement>()
+            .ok()?;
+        let maze = &self.shown_board().maze;
+        let topology = maze.options.topology.cells();
+        let (width, _) = topology.extent(maze.width, maze.height);
+        let scale = scale_for(width);
+        // The canvas may be drawn smaller than its size, e.g. on narrow screens
+        let shrink = (width * scale).ceil() / f64::from(canvas.client_width().max(1));
+        cell_at(maze, (x * shrink / scale, y * shrink / scale))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::perception::{generators::tests::TOPOLOGIES, maze::MazeOptions};
+
+    #[test]
+    fn points_find_the_cell_a_full_scan_would() {
+        for topology in TOPOLOGIES {
+            let options = MazeOptions {
+                topology,
+                ..MazeOptions::default()
+            };
+            let maze = Maze::generate(9, 7, 3, options);
+            let cells = topology.cells();
+            let scan = |point| {
+                (0..maze.height)
+                    .flat_map(|y| (0..maze.width).map(move |x| (x, y)))
+                    .find(|&(x, y)| contains(&cells.corners(x, y), point))
+            };
+
+            let (width, height) = cells.extent(maze.width, maze.height);
+            for i in -3..=(width * 20.0) as i32 + 3 {
+                for j in -3..=(height * 20.0) as i32 + 3 {
+                    // Off the grid lines, where either side would do
+                    let point = (f64::from(i) / 20.0 + 0.013, f64::from(j) / 20.0 + 0.007);
+                    assert_eq!(cell_at(&maze, point), scan(point), "{:?}", point);
+                }
+            }
+        }
+    }
+}
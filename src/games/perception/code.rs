@@ -0,0 +1,352 @@
+//! Shareable maze codes: a maze's layout packed into a few bytes and written
+//! as unpadded URL-safe base64, so it fits in a `?maze=` query parameter.
+//!
+//! Version 1 layout, integers as LEB128 varints unless noted:
+//!
+//! | field       | encoding                                                   |
+//! |-------------|------------------------------------------------------------|
+//! | version     | one byte, `1`                                              |
+//! | topology    | one byte: 0 square, 1 hex, 2 triangle                      |
+//! | flags       | one byte: bit 0 seed present, bit 1 ordered keys           |
+//! | width       | varint                                                     |
+//! | height      | varint                                                     |
+//! | start       | varint cell index (`y * width + x`)                        |
+//! | pairs       | one byte, the number of key/door pairs                     |
+//! | keys        | `pairs` varint cell indices                                |
+//! | doors       | `pairs` varint cell indices, the last being the exit      |
+//! | seed        | four bytes little-endian, if flagged                       |
+//! | walls       | one bit per shared wall, LSB first, up when set            |
+//!
+//! Shared walls are listed cell by cell in wall order, each taken from the
+//! lower-indexed of its two cells. Outer walls are always up and not stored.
+
+use super::{
+    maze::{Maze, MazeOptions, MAX_ASPECT, MAX_KEYS, MAX_SIZE},
+    solver,
+    topology::Topology,
+};
+use std::{convert::TryInto, fmt};
+
+const VERSION: u8 = 1;
+const HAS_SEED: u8 = 1;
+const ORDERED_KEYS: u8 = 2;
+// Longest side and most cells a code may describe: those of the largest maze
+// a level can have, and no more, to keep hostile codes cheap to reject
+const MAX_SIDE: usize = MAX_SIZE * MAX_ASPECT as usize;
+const MAX_CELLS: usize = MAX_SIZE * MAX_SIDE;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Why a maze code could not be read.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum CodeError {
+    NotBase64,
+    Truncated,
+    UnknownVersion(u8),
+    UnknownTopology(u8),
+    BadDimensions,
+    OutOfBounds,
+    BadPairs,
+    TrailingData,
+    Overflow,
+    Unsolvable,
+}
+
+impl fmt::Display for CodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotBase64 => write!(f, "maze code is not URL-safe base64"),
+            Self::Truncated => write!(f, "maze code is cut short"),
+            Self::UnknownVersion(v) => write!(f, "maze code version {v} is not supported"),
+            Self::UnknownTopology(t) => write!(f, "maze code has unknown cell shape {t}"),
+            Self::BadDimensions => write!(f, "maze code has impossible dimensions"),
+            Self::OutOfBounds => write!(f, "maze code places something outside the maze"),
+            Self::BadPairs => write!(f, "maze code has a bad number of keys and doors"),
+            Self::TrailingData => write!(f, "maze code has data past its end"),
+            Self::Overflow => write!(f, "maze code holds a number too large for a maze"),
+            Self::Unsolvable => write!(f, "maze code describes a maze with no way out"),
+        }
+    }
+}
+
+/// The shareable code for `maze`, seed included.
+pub(super) fn encode(maze: &Maze) -> String {
+    let mut bytes = vec![VERSION, topology_byte(maze.options.topology)];
+    bytes.push(
+        HAS_SEED
+            | if maze.options.ordered_keys {
+                ORDERED_KEYS
+            } else {
+                0
+            },
+    );
+    push_varint(&mut bytes, maze.width);
+    push_varint(&mut bytes, maze.height);
+    push_varint(&mut bytes, maze.cell_index(maze.start_position));
+    bytes.push(maze.keys.len() as u8);
+    for &position in maze.keys.iter().chain(&maze.doors) {
+        push_varint(&mut bytes, maze.cell_index(position));
+    }
+    bytes.extend_from_slice(&maze.seed.to_le_bytes());
+
+    let walls = shared_walls(maze);
+    let mut packed = vec![0u8; walls.len().div_ceil(8)];
+    for (i, &(wall, _)) in walls.iter().enumerate() {
+        if maze.walls[wall] {
+            packed[i / 8] |= 1 << (i % 8);
+        }
+    }
+    bytes.extend(packed);
+    to_base64(&bytes)
+}
+
+/// Rebuilds a maze from its code. Mazes shared without a seed get seed 0,
+/// which only decides the levels that follow.
+pub(super) fn decode(code: &str) -> Result<Maze, CodeError> {
+    let bytes = from_base64(code.trim())?;
+    let mut reader = Reader {
+        bytes: &bytes,
+        at: 0,
+    };
+
+    let version = reader.byte()?;
+    if version != VERSION {
+        return Err(CodeError::UnknownVersion(version));
+    }
+    let topology = match reader.byte()? {
+        0 => Topology::Square,
+        1 => Topology::Hex,
+        2 => Topology::Triangle,
+        other => return Err(CodeError::UnknownTopology(other)),
+    };
+    let flags = reader.byte()?;
+    let width = reader.varint()?;
+    let height = reader.varint()?;
+    if !(1..=MAX_SIDE).contains(&width)
+        || !(1..=MAX_SIDE).contains(&height)
+        || width * height > MAX_CELLS
+    {
+        return Err(CodeError::BadDimensions);
+    }
+    let cells = width * height;
+    let position = |reader: &mut Reader| {
+        let cell = reader.varint()?;
+        if cell >= cells {
+            return Err(CodeError::OutOfBounds);
+        }
+        Ok((cell % width, cell / width))
+    };
+
+    let start_position = position(&mut reader)?;
+    let pairs = reader.byte()? as usize;
+    if !(1..=MAX_KEYS).contains(&pairs) {
+        return Err(CodeError::BadPairs);
+    }
+    let keys = (0..pairs)
+        .map(|_| position(&mut reader))
+        .collect::<Result<Vec<_>, _>>()?;
+    let doors = (0..pairs)
+        .map(|_| position(&mut reader))
+        .collect::<Result<Vec<_>, _>>()?;
+    let seed = if flags & HAS_SEED != 0 {
+        u32::from_le_bytes(reader.take(4)?.try_into().unwrap())
+    } else {
+        0
+    };
+
+    let options = MazeOptions {
+        topology,
+        keys: pairs,
+        ordered_keys: flags & ORDERED_KEYS != 0,
+        ..MazeOptions::default()
+    };
+    let mut maze = Maze {
+        width,
+        height,
+        seed,
+        options,
+        walls: vec![true; cells * topology.cells().walls_per_cell()],
+        start_position,
+        keys,
+        doors,
+        par: 0,
+    };
+
+    let walls = shared_walls(&maze);
+    let packed = reader.take(walls.len().div_ceil(8))?;
+    if reader.at != bytes.len() {
+        return Err(CodeError::TrailingData);
+    }
+    for (i, &(wall, other_side)) in walls.iter().enumerate() {
+        if packed[i / 8] & (1 << (i % 8)) == 0 {
+            maze.walls[wall] = false;
+            maze.walls[other_side] = false;
+        }
+    }
+
+    let route = solver::solve(&maze).ok_or(CodeError::Unsolvable)?;
+    maze.par = route.len() - 1;
+    Ok(maze)
+}
+
+/// Every wall between two cells, once each, as its pair of indices into
+/// `maze.walls`: the lower-indexed cell's side first.
+fn shared_walls(maze: &Maze) -> Vec<(usize, usize)> {
+    let grid = maze.grid();
+    let walls_per_cell = maze.options.topology.cells().walls_per_cell();
+    (0..grid.cell_count())
+        .flat_map(|cell| {
+            grid.neighbours(cell)
+                .into_iter()
+                .filter(move |&(neighbour, _, _)| neighbour > cell)
+                .map(move |(neighbour, wall, nb_wall)| {
+                    (
+                        cell * walls_per_cell + wall,
+                        neighbour * walls_per_cell + nb_wall,
+                    )
+                })
+        })
+        .collect()
+}
+
+fn topology_byte(topology: Topology) -> u8 {
+    match topology {
+        Topology::Square => 0,
+        Topology::Hex => 1,
+        Topology::Triangle => 2,
+    }
+}
+
+fn push_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// Cursor over a decoded code.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], CodeError> {
+        let slice = self
+            .bytes
+            .get(self.at..self.at + n)
+            .ok_or(CodeError::Truncated)?;
+        self.at += n;
+        Ok(slice)
+    }
+
+    fn byte(&mut self) -> Result<u8, CodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<usize, CodeError> {
+        let mut value = 0usize;
+        // Anything past three bytes is larger than any maze needs
+        for shift in [0, 7, 14] {
+            let byte = self.byte()?;
+            value |= usize::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(CodeError::Overflow)
+    }
+}
+
+fn to_base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    out
+}
+
+fn from_base64(text: &str) -> Result<Vec<u8>, CodeError> {
+    let digits = text
+        .bytes()
+        .map(|c| ALPHABET.iter().position(|&a| a == c).map(|d| d as u32))
+        .collect::<Option<Vec<_>>>()
+        .ok_or(CodeError::NotBase64)?;
+    if digits.len() % 4 == 1 {
+        return Err(CodeError::NotBase64);
+    }
+
+    let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &d)| n | d << (18 - 6 * i));
+        for i in 0..chunk.len() - 1 {
+            bytes.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    #[test]
+    fn the_widest_level_fits_in_a_code() {
+        let options = MazeOptions {
+            aspect: MAX_ASPECT,
+            ..MazeOptions::default()
+        };
+        for aspect in [MAX_ASPECT, 1.0 / MAX_ASPECT] {
+            let (width, height) = MazeOptions { aspect, ..options }.dimensions(MAX_SIZE);
+            assert!(width <= MAX_SIDE && height <= MAX_SIDE);
+            assert!(width * height <= MAX_CELLS);
+        }
+    }
+
+    #[test]
+    fn wide_mazes_round_trip() {
+        let options = MazeOptions {
+            aspect: MAX_ASPECT,
+            ..MazeOptions::default()
+        };
+        let (width, height) = options.dimensions(100);
+        let maze = Maze::generate(width, height, 7, options);
+        let decoded = decode(&encode(&maze)).unwrap();
+        assert_same_layout(&decoded, &maze);
+    }
+
+    /// Codes carry the layout, not the options it was generated with.
+    pub(in crate::games::perception) fn assert_same_layout(decoded: &Maze, maze: &Maze) {
+        assert_eq!((decoded.width, decoded.height), (maze.width, maze.height));
+        assert_eq!(decoded.seed, maze.seed);
+        assert_eq!(decoded.options.topology, maze.options.topology);
+        assert_eq!(decoded.walls, maze.walls);
+        assert_eq!(decoded.start_position, maze.start_position);
+        assert_eq!((&decoded.keys, &decoded.doors), (&maze.keys, &maze.doors));
+        assert_eq!(decoded.par, maze.par);
+    }
+
+    #[test]
+    fn oversized_codes_are_rejected() {
+        let mut bytes = vec![VERSION, 0, 0];
+        push_varint(&mut bytes, MAX_SIDE + 1);
+        push_varint(&mut bytes, 1);
+        assert_eq!(decode(&to_base64(&bytes)), Err(CodeError::BadDimensions));
+
+        // Each side fits, but not both at once
+        let mut bytes = vec![VERSION, 0, 0];
+        push_varint(&mut bytes, MAX_SIDE);
+        push_varint(&mut bytes, MAX_SIDE);
+        assert_eq!(decode(&to_base64(&bytes)), Err(CodeError::BadDimensions));
+    }
+}
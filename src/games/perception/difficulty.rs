@@ -0,0 +1,250 @@
+//! The difficulty curve: what each level looks like. A table sets levels
+//! one by one from level 1, and a growth rule carries on past its end.
+//! Loaded from JSON, e.g.
+//!
+//! ```json
+//! {
+//!   "levels": [
+//!     { "size": 2, "time_limit": 120 },
+//!     { "size": 4, "keys": 2, "algorithm": "Prim" },
+//!     { "penalty": { "Lives": 3 } }
+//!   ],
+//!   "growth": { "size": 1, "time_limit": -10, "min_time_limit": 60 }
+//! }
+//! ```
+//!
+//! A level the table leaves a field unset on takes it from the level
+//! before: size and time limit grown by the growth rule, anything else
+//! as it was. Algorithm, keys and penalty never set fall back to the
+//! player's own choices.
+
+use super::{
+    board::{Penalty, MAX_LIVES, MAX_TIME_PENALTY},
+    generators::Algorithm,
+    maze::{MazeOptions, MAX_KEYS, MAX_SIZE},
+};
+use serde::{Deserialize, Serialize};
+
+// Shorter side and time limit of level 1 unless the table says otherwise
+const FIRST_SIZE: usize = 2;
+const FIRST_TIME_LIMIT: i32 = 300;
+
+/// Everything one level is set up with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct Level {
+    // Length of the maze's shorter side
+    pub(super) size: usize,
+    // Seconds on the clock when the level starts
+    pub(super) time_limit: i32,
+    pub(super) options: MazeOptions,
+    pub(super) penalty: Penalty,
+}
+
+/// One row of the table; unset fields follow from the level before.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(super) struct Step {
+    size: Option<usize>,
+    time_limit: Option<i32>,
+    algorithm: Option<Algorithm>,
+    keys: Option<usize>,
+    penalty: Option<Penalty>,
+}
+
+/// How levels past the table change from one to the next.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(super) struct Growth {
+    // Added to the shorter side each level
+    size: usize,
+    // Added to the time limit each level; negative shortens it
+    time_limit: i32,
+    // Floor for a shrinking time limit
+    min_time_limit: i32,
+}
+
+impl Default for Growth {
+    fn default() -> Self {
+        Self {
+            size: 1,
+            time_limit: 0,
+            min_time_limit: 30,
+        }
+    }
+}
+
+/// Maps level numbers to how they are set up. The default grows the maze
+/// by one each level with five minutes on the clock throughout.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(super) struct Curve {
+    levels: Vec<Step>,
+    growth: Growth,
+}
+
+impl Curve {
+    /// Rejects curves with sizes, time limits, key counts or penalties no
+    /// level can use.
+    pub(super) fn validate(&self) -> Result<(), String> {
+        if self.growth.min_time_limit < 1 {
+            return Err("growth.min_time_limit must be at least 1".to_string());
+        }
+        for (i, step) in self.levels.iter().enumerate() {
+            let level = i + 1;
+            if step
+                .size
+                .is_some_and(|size| !(FIRST_SIZE..=MAX_SIZE).contains(&size))
+            {
+                return Err(format!(
+                    "level {level}: size must be {FIRST_SIZE} to {MAX_SIZE}"
+                ));
+            }
+            if step.time_limit.is_some_and(|time| time < 1) {
+                return Err(format!("level {level}: time_limit must be at least 1"));
+            }
+            if step
+                .keys
+                .is_some_and(|keys| !(1..=MAX_KEYS).contains(&keys))
+            {
+                return Err(format!("level {level}: keys must be 1 to {MAX_KEYS}"));
+            }
+            if step
+                .penalty
+                .is_some_and(|penalty| penalty != penalty.clamped())
+            {
+                return Err(format!(
+                    "level {level}: penalty must be 1 to {MAX_TIME_PENALTY} seconds or 1 to {MAX_LIVES} lives"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Level `number` (from 1), filling in what the curve leaves unset
+    /// from the player's `options` and `penalty`.
+    pub(super) fn level(&self, number: usize, options: MazeOptions, penalty: Penalty) -> Level {
+        let mut size = FIRST_SIZE;
+        let mut time_limit = FIRST_TIME_LIMIT;
+        let mut chosen = Step::default();
+        for n in 1..=number.max(1) {
+            if n > 1 {
+                size = (size + self.growth.size).min(MAX_SIZE);
+                // Shrinking stops at the floor, or at a lower limit the table set
+                let floor = self.growth.min_time_limit.min(time_limit);
+                time_limit = time_limit.saturating_add(self.growth.time_limit).max(floor);
+            }
+            if let Some(step) = self.levels.get(n - 1) {
+                size = step.size.unwrap_or(size);
+                time_limit = step.time_limit.unwrap_or(time_limit);
+                chosen.algorithm = step.algorithm.or(chosen.algorithm);
+                chosen.keys = step.keys.or(chosen.keys);
+                chosen.penalty = step.penalty.or(chosen.penalty);
+            }
+        }
+
+        Level {
+            size,
+            time_limit,
+            options: MazeOptions {
+                algorithm: chosen.algorithm.unwrap_or(options.algorithm),
+                keys: chosen.keys.unwrap_or(options.keys),
+                ..options
+            },
+            penalty: chosen.penalty.unwrap_or(penalty),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve(json: &str) -> Curve {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn level(curve: &Curve, number: usize) -> Level {
+        curve.level(number, MazeOptions::default(), Penalty::Restart)
+    }
+
+    #[test]
+    fn rows_take_what_they_leave_unset_from_earlier_rows() {
+        let curve = curve(
+            r#"{
+                "levels": [
+                    { "size": 4, "time_limit": 200, "keys": 2, "algorithm": "Prim" },
+                    { "time_limit": 100 },
+                    { "penalty": { "Lives": 3 } }
+                ]
+            }"#,
+        );
+
+        let second = level(&curve, 2);
+        assert_eq!((second.size, second.time_limit), (5, 100));
+        assert_eq!(second.options.keys, 2);
+        assert_eq!(second.options.algorithm, Algorithm::Prim);
+        assert_eq!(second.penalty, Penalty::Restart);
+
+        let third = level(&curve, 3);
+        assert_eq!((third.size, third.time_limit), (6, 100));
+        assert_eq!(third.options.keys, 2);
+        assert_eq!(third.penalty, Penalty::Lives(3));
+    }
+
+    #[test]
+    fn growth_carries_on_past_the_table() {
+        let curve = curve(
+            r#"{
+                "levels": [{ "size": 3, "time_limit": 200 }],
+                "growth": { "size": 2, "time_limit": -50, "min_time_limit": 60 }
+            }"#,
+        );
+        let setups: Vec<(usize, i32)> = (1..=5)
+            .map(|number| level(&curve, number))
+            .map(|level| (level.size, level.time_limit))
+            .collect();
+        assert_eq!(
+            setups,
+            vec![(3, 200), (5, 150), (7, 100), (9, 60), (11, 60)]
+        );
+        assert_eq!(level(&curve, 1000).size, MAX_SIZE);
+    }
+
+    #[test]
+    fn time_limits_shrink_no_further_than_the_floor() {
+        let shrinking = r#"{ "growth": { "time_limit": -100, "min_time_limit": 90 } }"#;
+        let limits: Vec<i32> = (1..=4)
+            .map(|number| level(&curve(shrinking), number).time_limit)
+            .collect();
+        assert_eq!(limits, vec![FIRST_TIME_LIMIT, 200, 100, 90]);
+
+        // A lower limit from the table holds, rather than jumping up to it
+        let below = r#"{
+            "levels": [{ "time_limit": 20 }],
+            "growth": { "time_limit": -10, "min_time_limit": 60 }
+        }"#;
+        assert_eq!(level(&curve(below), 2).time_limit, 20);
+        assert_eq!(level(&curve(below), 5).time_limit, 20);
+    }
+
+    #[test]
+    fn rows_no_level_can_use_are_rejected() {
+        let bad = [
+            r#"{ "levels": [{ "size": 1 }] }"#,
+            r#"{ "levels": [{ "size": 100000 }] }"#,
+            r#"{ "levels": [{}, { "keys": 0 }] }"#,
+            r#"{ "levels": [{ "keys": 6 }] }"#,
+            r#"{ "levels": [{ "time_limit": 0 }] }"#,
+            r#"{ "levels": [{ "penalty": { "Time": 0 } }] }"#,
+            r#"{ "levels": [{ "penalty": { "Lives": 0 } }] }"#,
+            r#"{ "levels": [{ "penalty": { "Lives": 10 } }] }"#,
+            r#"{ "growth": { "min_time_limit": 0 } }"#,
+        ];
+        for json in bad {
+            assert!(curve(json).validate().is_err(), "{}", json);
+        }
+        assert_eq!(curve("{}").validate(), Ok(()));
+        let fine = r#"{ "levels": [{ "size": 2, "keys": 5, "penalty": { "Time": 1 } }] }"#;
+        assert_eq!(curve(fine).validate(), Ok(()));
+    }
+}
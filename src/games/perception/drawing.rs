@@ -0,0 +1,379 @@
+//! DOM-free pictures of a maze: ASCII art for tests and bug reports, and a
+//! standalone SVG document for printing.
+
+use super::{maze::Maze, solver, topology::Topology};
+use std::{collections::HashSet, fmt::Write};
+
+// Pixels per cell width in SVG output
+const SVG_SCALE: f64 = 40.0;
+// Key/door pair colours, matching the game's stylesheet
+pub(super) const PAIR_COLOURS: [&str; 5] = ["#b8860b", "#b22222", "#1e50c8", "#1e7832", "#7828a0"];
+
+/// What to draw over the bare walls.
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct Overlay {
+    /// The optimal route from the start through the exit.
+    pub(super) solution: bool,
+    /// The start, keys and doors.
+    pub(super) items: bool,
+}
+
+/// ASCII art of a square-celled maze, three characters per cell:
+///
+/// ```text
+/// +---+---+---+
+/// | S   a |   |
+/// +---+   +   +
+/// | A   .   B |
+/// +---+---+---+
+/// ```
+///
+/// With items shown, `S` is the start, `a`, `b`, … are keys and `A`, `B`, …
+/// their doors, the last door being the exit. Solution cells show as `.`.
+/// Hex and triangle mazes have no ASCII form.
+pub(super) fn ascii(maze: &Maze, overlay: Overlay) -> Option<String> {
+    if maze.options.topology != Topology::Square {
+        return None;
+    }
+    let route = solution_cells(maze, overlay);
+    let wall = |x, y, side| maze.walls[maze.cell_index((x, y)) * 4 + side];
+
+    let mut out = String::new();
+    for y in 0..maze.height {
+        for x in 0..maze.width {
+            out.push_str(if wall(x, y, 0) { "+---" } else { "+   " });
+        }
+        out.push_str("+\n");
+        for x in 0..maze.width {
+            out.push(if wall(x, y, 3) { '|' } else { ' ' });
+            let mark = if overlay.items {
+                item_mark(maze, (x, y))
+            } else {
+                None
+            };
+            let mark = mark.or_else(|| route.contains(&(x, y)).then_some('.'));
+            let _ = write!(out, " {} ", mark.unwrap_or(' '));
+        }
+        out.push_str("|\n");
+    }
+    out.push_str(&"+---".repeat(maze.width));
+    out.push_str("+\n");
+    Some(out)
+}
+
+/// A standalone SVG document of the maze, for any cell shape.
+pub(super) fn svg(maze: &Maze, overlay: Overlay) -> String {
+    let topology = maze.options.topology.cells();
+    let (width, height) = topology.extent(maze.width, maze.height);
+    let margin = 0.25;
+    let point = |(x, y): (f64, f64)| format!("{:.2},{:.2}", x * SVG_SCALE, y * SVG_SCALE);
+
+    let (left, top) = (-margin * SVG_SCALE, -margin * SVG_SCALE);
+    let (outer_width, outer_height) = (
+        (width + 2.0 * margin) * SVG_SCALE,
+        (height + 2.0 * margin) * SVG_SCALE,
+    );
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+         viewBox=\"{left:.2} {top:.2} {outer_width:.2} {outer_height:.2}\" \
+         width=\"{outer_width:.0}\" height=\"{outer_height:.0}\">"
+    );
+    let _ = writeln!(
+        out,
+        "<rect x=\"{left:.2}\" y=\"{top:.2}\" width=\"{outer_width:.2}\" \
+         height=\"{outer_height:.2}\" fill=\"white\"/>"
+    );
+
+    // Each shared wall is drawn once, from the lower-indexed cell
+    let grid = maze.grid();
+    let walls_per_cell = topology.walls_per_cell();
+    let mut path = String::new();
+    for cell in 0..grid.cell_count() {
+        let (x, y) = grid.position(cell);
+        let corners = topology.corners(x, y);
+        let neighbours = grid.neighbours(cell);
+        for wall in 0..walls_per_cell {
+            let neighbour = neighbours.iter().find(|&&(_, w, _)| w == wall);
+            let drawn_here = neighbour.is_none_or(|&(n, _, _)| n > cell);
+            if drawn_here && maze.walls[cell * walls_per_cell + wall] {
+                let (from, to) = (corners[wall], corners[(wall + 1) % corners.len()]);
+                let _ = write!(path, "M{}L{}", point(from), point(to));
+            }
+        }
+    }
+
+    if overlay.solution {
+        let route = solver::solve(maze).unwrap_or_default();
+        let points = route
+            .iter()
+            .map(|&position| point(maze.centre(position)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let _ = writeln!(
+            out,
+            "<polyline points=\"{points}\" fill=\"none\" stroke=\"#a532ff\" \
+             stroke-width=\"{:.1}\" stroke-linejoin=\"round\" stroke-linecap=\"round\" \
+             opacity=\"0.6\"/>",
+            SVG_SCALE * 0.15
+        );
+    }
+
+    let _ = writeln!(
+        out,
+        "<path d=\"{path}\" stroke=\"black\" stroke-width=\"{:.1}\" stroke-linecap=\"round\"/>",
+        SVG_SCALE * 0.08
+    );
+
+    if overlay.items {
+        let radius = SVG_SCALE * 0.2;
+        let (sx, sy) = maze.centre(maze.start_position);
+        let _ = writeln!(
+            out,
+            "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{radius:.1}\" fill=\"none\" \
+             stroke=\"black\" stroke-width=\"{:.1}\"/>",
+            sx * SVG_SCALE,
+            sy * SVG_SCALE,
+            SVG_SCALE * 0.06
+        );
+        for (i, (&key, &door)) in maze.keys.iter().zip(&maze.doors).enumerate() {
+            let colour = PAIR_COLOURS[i % PAIR_COLOURS.len()];
+            let (kx, ky) = maze.centre(key);
+            let _ = writeln!(
+                out,
+                "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{radius:.1}\" fill=\"{colour}\"/>",
+                kx * SVG_SCALE,
+                ky * SVG_SCALE
+            );
+            let (dx, dy) = maze.centre(door);
+            let _ = writeln!(
+                out,
+                "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{colour}\"/>",
+                dx * SVG_SCALE - radius,
+                dy * SVG_SCALE - radius,
+                2.0 * radius,
+                2.0 * radius
+            );
+        }
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
+
+/// Cells on the optimal route, if the overlay asks for them.
+fn solution_cells(maze: &Maze, overlay: Overlay) -> HashSet<(usize, usize)> {
+    if !overlay.solution {
+        return HashSet::new();
+    }
+    solver::solve(maze)
+        .unwrap_or_default()
+        .into_iter()
+        .collect()
+}
+
+fn item_mark(maze: &Maze, position: (usize, usize)) -> Option<char> {
+    if position == maze.start_position {
+        Some('S')
+    } else if let Some(key) = maze.key_at(position) {
+        Some(char::from(b'a' + key as u8))
+    } else {
+        maze.door_at(position)
+            .map(|door| char::from(b'A' + door as u8))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::perception::{
+        generators::{tests::TOPOLOGIES, Algorithm},
+        maze::MazeOptions,
+    };
+
+    const OVERLAYS: [Overlay; 4] = [
+        Overlay {
+            solution: false,
+            items: false,
+        },
+        Overlay {
+            solution: true,
+            items: false,
+        },
+        Overlay {
+            solution: false,
+            items: true,
+        },
+        Overlay {
+            solution: true,
+            items: true,
+        },
+    ];
+
+    fn two_key_maze() -> Maze {
+        let options = MazeOptions {
+            algorithm: Algorithm::Prim,
+            keys: 2,
+            ..MazeOptions::default()
+        };
+        Maze::generate(6, 4, 42, options)
+    }
+
+    /// Names of the elements in `svg`, in document order, after checking
+    /// that its tags nest, its attributes are quoted and its numbers are
+    /// finite.
+    fn elements(svg: &str) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut open = Vec::new();
+        let mut rest = svg.trim_end();
+        while !rest.is_empty() {
+            let text_end = rest.find('<').unwrap_or(rest.len());
+            assert!(
+                rest[..text_end].trim().is_empty(),
+                "text between tags: {:?}",
+                &rest[..text_end]
+            );
+            rest = &rest[text_end..];
+            if rest.is_empty() {
+                break;
+            }
+            let tag_end = rest.find('>').expect("unclosed tag");
+            let tag = &rest[1..tag_end];
+            rest = &rest[tag_end + 1..];
+            assert!(!tag.contains('<'), "tag inside a tag: {:?}", tag);
+
+            if let Some(name) = tag.strip_prefix('/') {
+                assert_eq!(open.pop().as_deref(), Some(name), "mismatched close tag");
+                continue;
+            }
+            let (tag, empty) = match tag.strip_suffix('/') {
+                Some(tag) => (tag, true),
+                None => (tag, false),
+            };
+            let (name, mut attributes) = tag.split_once(' ').unwrap_or((tag, ""));
+            while !attributes.trim().is_empty() {
+                let (attribute, value) = attributes.trim_start().split_once("=\"").unwrap();
+                assert!(
+                    !attribute.contains(char::is_whitespace),
+                    "unquoted attribute"
+                );
+                let (value, after) = value.split_once('"').unwrap();
+                for number in value.split(|c: char| " ,ML".contains(c)) {
+                    if let Ok(number) = number.parse::<f64>() {
+                        assert!(number.is_finite(), "{} is {}", attribute, number);
+                    }
+                }
+                attributes = after;
+            }
+
+            assert!(open.is_empty() == names.is_empty(), "more than one root");
+            names.push(name.to_string());
+            if !empty {
+                open.push(name.to_string());
+            }
+        }
+        assert!(open.is_empty(), "unclosed elements: {:?}", open);
+        names
+    }
+
+    #[test]
+    fn ascii_shows_the_bare_walls() {
+        assert_eq!(
+            ascii(&two_key_maze(), Overlay::default()).unwrap(),
+            concat!(
+                "+---+---+---+---+---+---+\n",
+                "|           |           |\n",
+                "+---+   +---+   +---+---+\n",
+                "|           |       |   |\n",
+                "+---+   +---+   +---+   +\n",
+                "|                       |\n",
+                "+---+   +---+   +---+---+\n",
+                "|       |               |\n",
+                "+---+---+---+---+---+---+\n",
+            )
+        );
+    }
+
+    #[test]
+    fn ascii_marks_the_solution() {
+        let solution = Overlay {
+            solution: true,
+            ..Overlay::default()
+        };
+        assert_eq!(
+            ascii(&two_key_maze(), solution).unwrap(),
+            concat!(
+                "+---+---+---+---+---+---+\n",
+                "|     .   . | .   .   . |\n",
+                "+---+   +---+   +---+---+\n",
+                "|     .     | .     |   |\n",
+                "+---+   +---+   +---+   +\n",
+                "|     .   .   .         |\n",
+                "+---+   +---+   +---+---+\n",
+                "|       |               |\n",
+                "+---+---+---+---+---+---+\n",
+            )
+        );
+    }
+
+    #[test]
+    fn ascii_items_are_drawn_over_the_solution() {
+        let both = Overlay {
+            solution: true,
+            items: true,
+        };
+        assert_eq!(
+            ascii(&two_key_maze(), both).unwrap(),
+            concat!(
+                "+---+---+---+---+---+---+\n",
+                "|     .   B | a   .   S |\n",
+                "+---+   +---+   +---+---+\n",
+                "|     .     | .     |   |\n",
+                "+---+   +---+   +---+   +\n",
+                "|     b   A   .         |\n",
+                "+---+   +---+   +---+---+\n",
+                "|       |               |\n",
+                "+---+---+---+---+---+---+\n",
+            )
+        );
+    }
+
+    #[test]
+    fn ascii_only_draws_square_cells() {
+        for topology in [Topology::Hex, Topology::Triangle] {
+            let options = MazeOptions {
+                topology,
+                ..MazeOptions::default()
+            };
+            assert_eq!(
+                ascii(&Maze::generate(4, 4, 1, options), Overlay::default()),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn svg_is_well_formed_for_every_topology_and_overlay() {
+        for topology in TOPOLOGIES {
+            let options = MazeOptions {
+                topology,
+                keys: 3,
+                ..MazeOptions::default()
+            };
+            let maze = Maze::generate(9, 7, 3, options);
+            for overlay in OVERLAYS {
+                let names = elements(&svg(&maze, overlay));
+                let count = |name: &str| names.iter().filter(|&n| n == name).count();
+
+                assert_eq!(names[0], "svg");
+                assert_eq!(count("path"), 1);
+                assert_eq!(count("polyline"), usize::from(overlay.solution));
+                let items = if overlay.items { maze.keys.len() } else { 0 };
+                assert_eq!(count("circle"), items + usize::from(overlay.items));
+                assert_eq!(count("rect"), 1 + items);
+            }
+        }
+    }
+}
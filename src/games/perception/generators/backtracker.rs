@@ -0,0 +1,37 @@
+use super::{Grid, MazeGenerator, Rng};
+
+/// Iterative recursive-backtracker (randomised depth-first search).
+pub(super) struct Backtracker;
+
+impl MazeGenerator for Backtracker {
+    fn carve(&self, grid: &mut Grid, rng: &mut Rng) -> usize {
+        let mut visited = vec![false; grid.cell_count()];
+
+        // Pick a random starting cell (row, then column)
+        let row = rng.below(grid.rows());
+        let start = grid.row_cells(row).start + rng.below(grid.row_cells(row).len());
+
+        let mut stack = vec![start];
+        visited[start] = true;
+
+        while let Some(&cell) = stack.last() {
+            let mut neighbours: Vec<usize> = grid
+                .neighbours(cell)
+                .into_iter()
+                .map(|(neighbour, _, _)| neighbour)
+                .filter(|&neighbour| !visited[neighbour])
+                .collect();
+
+            if neighbours.is_empty() {
+                stack.pop();
+            } else {
+                rng.shuffle(&mut neighbours);
+                let next = neighbours[0];
+                grid.carve(cell, next);
+                visited[next] = true;
+                stack.push(next);
+            }
+        }
+        start
+    }
+}
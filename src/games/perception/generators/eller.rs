@@ -0,0 +1,65 @@
+use super::{DisjointSet, Grid, MazeGenerator, Rng};
+
+/// Eller's: builds the maze one row at a time, randomly joining neighbouring
+/// sets within a row and carrying every set down at least once.
+pub(super) struct Eller;
+
+impl MazeGenerator for Eller {
+    fn carve(&self, grid: &mut Grid, rng: &mut Rng) -> usize {
+        let mut sets = DisjointSet::new(grid.cell_count());
+        let last_row = grid.rows() - 1;
+
+        for row in 0..grid.rows() {
+            // Join horizontal neighbours; the last row joins every distinct set.
+            for cell in grid.row_cells(row) {
+                for (neighbour, _, _) in grid.neighbours(cell) {
+                    if neighbour > cell
+                        && grid.row(neighbour) == row
+                        && (row == last_row || rng.next_f64() < 0.5)
+                        && sets.union(cell, neighbour)
+                    {
+                        grid.carve(cell, neighbour);
+                    }
+                }
+            }
+            if row == last_row {
+                break;
+            }
+
+            // Vertical passages: every set continues into the next row at
+            // least once, other cells drop down at random.
+            let view: &Grid = grid;
+            let mut downs: Vec<(usize, usize)> = view
+                .row_cells(row)
+                .flat_map(|cell| {
+                    view.neighbours(cell)
+                        .into_iter()
+                        .filter(move |&(neighbour, _, _)| view.row(neighbour) == row + 1)
+                        .map(move |(neighbour, _, _)| (cell, neighbour))
+                })
+                .collect();
+            rng.shuffle(&mut downs);
+
+            let mut carried = Vec::new();
+            for (cell, below) in downs {
+                // A cell below may already be reached from this set through
+                // another cell when rows interlock, as in hex grids.
+                let set = sets.find(cell);
+                if (!carried.contains(&set) || rng.next_f64() < 0.5) && sets.union(cell, below) {
+                    carried.push(set);
+                    grid.carve(cell, below);
+                }
+            }
+        }
+
+        // Joins anything the row sweep left apart.
+        for cell in 0..grid.cell_count() {
+            for (neighbour, _, _) in grid.neighbours(cell) {
+                if sets.union(cell, neighbour) {
+                    grid.carve(cell, neighbour);
+                }
+            }
+        }
+        0
+    }
+}
@@ -0,0 +1,56 @@
+use super::{Grid, MazeGenerator, Rng};
+
+/// Hunt-and-kill: random walks through unvisited cells; when a walk is stuck,
+/// scans for an unvisited cell beside the maze and continues from there.
+pub(super) struct HuntAndKill;
+
+impl MazeGenerator for HuntAndKill {
+    fn carve(&self, grid: &mut Grid, rng: &mut Rng) -> usize {
+        let mut visited = vec![false; grid.cell_count()];
+        let start = rng.below(grid.cell_count());
+        visited[start] = true;
+
+        // Rows above this one are fully visited, so hunts start here.
+        let mut hunt_row = 0;
+        let mut current = Some(start);
+        while let Some(cell) = current {
+            // Kill: walk to a random unvisited neighbour.
+            let unvisited: Vec<usize> = grid
+                .neighbours(cell)
+                .into_iter()
+                .map(|(neighbour, _, _)| neighbour)
+                .filter(|&neighbour| !visited[neighbour])
+                .collect();
+            if !unvisited.is_empty() {
+                let next = unvisited[rng.below(unvisited.len())];
+                grid.carve(cell, next);
+                visited[next] = true;
+                current = Some(next);
+                continue;
+            }
+
+            // Hunt: first unvisited cell touching the maze.
+            while hunt_row < grid.rows() && grid.row_cells(hunt_row).all(|c| visited[c]) {
+                hunt_row += 1;
+            }
+            let found = (hunt_row..grid.rows())
+                .flat_map(|row| grid.row_cells(row))
+                .filter(|&c| !visited[c])
+                .find_map(|candidate| {
+                    let joined: Vec<usize> = grid
+                        .neighbours(candidate)
+                        .into_iter()
+                        .map(|(neighbour, _, _)| neighbour)
+                        .filter(|&neighbour| visited[neighbour])
+                        .collect();
+                    Some((candidate, joined)).filter(|(_, joined)| !joined.is_empty())
+                });
+            current = found.map(|(candidate, joined)| {
+                grid.carve(candidate, joined[rng.below(joined.len())]);
+                visited[candidate] = true;
+                candidate
+            });
+        }
+        start
+    }
+}
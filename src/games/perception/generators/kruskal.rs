@@ -0,0 +1,27 @@
+use super::{DisjointSet, Grid, MazeGenerator, Rng};
+
+/// Randomised Kruskal's: opens walls in random order whenever they join two
+/// separate regions.
+pub(super) struct Kruskal;
+
+impl MazeGenerator for Kruskal {
+    fn carve(&self, grid: &mut Grid, rng: &mut Rng) -> usize {
+        let mut edges: Vec<(usize, usize)> = (0..grid.cell_count())
+            .flat_map(|cell| {
+                grid.neighbours(cell)
+                    .into_iter()
+                    .filter(move |&(neighbour, _, _)| neighbour > cell)
+                    .map(move |(neighbour, _, _)| (cell, neighbour))
+            })
+            .collect();
+        rng.shuffle(&mut edges);
+
+        let mut sets = DisjointSet::new(grid.cell_count());
+        for (cell, neighbour) in edges {
+            if sets.union(cell, neighbour) {
+                grid.carve(cell, neighbour);
+            }
+        }
+        0
+    }
+}
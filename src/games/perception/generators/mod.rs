@@ -0,0 +1,237 @@
+mod backtracker;
+mod eller;
+mod hunt_and_kill;
+mod kruskal;
+mod prim;
+mod wilson;
+
+use super::maze::Grid;
+use crate::games::rng::Rng;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// Carves a perfect maze (a spanning tree of the grid) out of a fully walled grid.
+pub(super) trait MazeGenerator {
+    /// Carves passages into `grid` and returns the cell generation started from.
+    fn carve(&self, grid: &mut Grid, rng: &mut Rng) -> usize;
+}
+
+/// Maze generation algorithm, selectable from JS.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Algorithm {
+    /// Depth-first search: long, winding corridors with few branches.
+    #[default]
+    Backtracker,
+    /// Randomised Prim's: many short dead ends radiating from the start.
+    Prim,
+    /// Randomised Kruskal's: evenly spread, short dead ends.
+    Kruskal,
+    /// Wilson's: uniform spanning tree, no structural bias.
+    Wilson,
+    /// Eller's: built row by row, with a horizontal texture.
+    Eller,
+    /// Hunt-and-kill: long corridors like the backtracker, but more branches.
+    HuntAndKill,
+}
+
+impl Algorithm {
+    pub(super) fn generator(self) -> &'static dyn MazeGenerator {
+        match self {
+            Self::Backtracker => &backtracker::Backtracker,
+            Self::Prim => &prim::Prim,
+            Self::Kruskal => &kruskal::Kruskal,
+            Self::Wilson => &wilson::Wilson,
+            Self::Eller => &eller::Eller,
+            Self::HuntAndKill => &hunt_and_kill::HuntAndKill,
+        }
+    }
+}
+
+/// Turns a perfect maze into a braided one: each dead end is opened into a
+/// neighbour with probability `factor`, creating loops.
+pub(super) fn braid(grid: &mut Grid, factor: f64, rng: &mut Rng) {
+    let mut dead_ends: Vec<usize> = (0..grid.cell_count())
+        .filter(|&cell| grid.open_neighbours(cell).len() == 1)
+        .collect();
+    rng.shuffle(&mut dead_ends);
+
+    for cell in dead_ends {
+        // An earlier removal may already have opened this one up.
+        if grid.open_neighbours(cell).len() != 1 || rng.next_f64() >= factor {
+            continue;
+        }
+        let open = grid.open_neighbours(cell);
+        let closed: Vec<usize> = grid
+            .neighbours(cell)
+            .into_iter()
+            .map(|(neighbour, _, _)| neighbour)
+            .filter(|neighbour| !open.contains(neighbour))
+            .collect();
+        // Prefer joining two dead ends, which removes both at once.
+        let dead: Vec<usize> = closed
+            .iter()
+            .copied()
+            .filter(|&neighbour| grid.open_neighbours(neighbour).len() == 1)
+            .collect();
+        let choices = if dead.is_empty() { &closed } else { &dead };
+        if !choices.is_empty() {
+            grid.carve(cell, choices[rng.below(choices.len())]);
+        }
+    }
+}
+
+/// Union-find over cell indices, for the set-merging generators.
+struct DisjointSet {
+    parent: Vec<usize>,
+}
+
+impl DisjointSet {
+    fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+        }
+    }
+
+    fn find(&mut self, mut cell: usize) -> usize {
+        while self.parent[cell] != cell {
+            self.parent[cell] = self.parent[self.parent[cell]];
+            cell = self.parent[cell];
+        }
+        cell
+    }
+
+    /// Merges the sets of `a` and `b`, returning false if they were already joined.
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        self.parent[b] = a;
+        true
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use crate::games::perception::topology::Topology;
+
+    pub(in crate::games::perception) const ALGORITHMS: [Algorithm; 6] = [
+        Algorithm::Backtracker,
+        Algorithm::Prim,
+        Algorithm::Kruskal,
+        Algorithm::Wilson,
+        Algorithm::Eller,
+        Algorithm::HuntAndKill,
+    ];
+    pub(in crate::games::perception) const TOPOLOGIES: [Topology; 3] =
+        [Topology::Square, Topology::Hex, Topology::Triangle];
+
+    /// Passages in `grid`, each counted once, after checking both cells
+    /// either side of every wall agree on it.
+    fn passages(grid: &Grid) -> usize {
+        let mut passages = 0;
+        for cell in 0..grid.cell_count() {
+            let open = grid.open_neighbours(cell);
+            for neighbour in &open {
+                assert!(
+                    grid.open_neighbours(*neighbour).contains(&cell),
+                    "wall between {} and {} is down on one side only",
+                    cell,
+                    neighbour
+                );
+            }
+            passages += open.iter().filter(|&&neighbour| neighbour > cell).count();
+        }
+        passages
+    }
+
+    fn connected(grid: &Grid) -> bool {
+        let mut seen = vec![false; grid.cell_count()];
+        let mut stack = vec![0];
+        seen[0] = true;
+        while let Some(cell) = stack.pop() {
+            for neighbour in grid.open_neighbours(cell) {
+                if !seen[neighbour] {
+                    seen[neighbour] = true;
+                    stack.push(neighbour);
+                }
+            }
+        }
+        seen.into_iter().all(|seen| seen)
+    }
+
+    /// Whether every cell of `grid` is reachable and by exactly one route.
+    pub(in crate::games::perception) fn is_spanning_tree(grid: &Grid) -> bool {
+        passages(grid) == grid.cell_count() - 1 && connected(grid)
+    }
+
+    fn dead_ends(grid: &Grid) -> usize {
+        (0..grid.cell_count())
+            .filter(|&cell| grid.open_neighbours(cell).len() == 1)
+            .count()
+    }
+
+    #[test]
+    fn every_algorithm_carves_a_spanning_tree() {
+        for algorithm in ALGORITHMS {
+            for topology in TOPOLOGIES {
+                for (width, height) in [(2, 2), (2, 7), (9, 6), (16, 3)] {
+                    for seed in 0..5 {
+                        let mut grid = Grid::new(topology, width, height);
+                        let origin = algorithm.generator().carve(&mut grid, &mut Rng::new(seed));
+                        assert!(origin < grid.cell_count());
+                        assert!(
+                            is_spanning_tree(&grid),
+                            "{:?} on a {}×{} {:?} grid, seed {}",
+                            algorithm,
+                            width,
+                            height,
+                            topology,
+                            seed
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn braiding_only_opens_walls_and_keeps_the_maze_connected() {
+        for topology in TOPOLOGIES {
+            for seed in 0..5 {
+                let mut grid = Grid::new(topology, 9, 6);
+                Algorithm::Backtracker
+                    .generator()
+                    .carve(&mut grid, &mut Rng::new(seed));
+                let before: Vec<Vec<usize>> = (0..grid.cell_count())
+                    .map(|cell| grid.open_neighbours(cell))
+                    .collect();
+                let dead_ends_before = dead_ends(&grid);
+
+                braid(&mut grid, 0.5, &mut Rng::new(seed));
+
+                for (cell, open) in before.iter().enumerate() {
+                    let now = grid.open_neighbours(cell);
+                    assert!(open.iter().all(|neighbour| now.contains(neighbour)));
+                }
+                assert!(passages(&grid) >= grid.cell_count() - 1);
+                assert!(connected(&grid));
+                assert!(dead_ends(&grid) <= dead_ends_before);
+            }
+        }
+    }
+
+    #[test]
+    fn full_braiding_leaves_no_dead_ends() {
+        for topology in TOPOLOGIES {
+            let mut grid = Grid::new(topology, 9, 6);
+            Algorithm::Kruskal
+                .generator()
+                .carve(&mut grid, &mut Rng::new(1));
+            braid(&mut grid, 1.0, &mut Rng::new(1));
+            assert_eq!(dead_ends(&grid), 0, "{:?}", topology);
+        }
+    }
+}
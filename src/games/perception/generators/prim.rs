@@ -0,0 +1,35 @@
+use super::{Grid, MazeGenerator, Rng};
+
+/// Randomised Prim's: grows the maze from one cell by opening a random
+/// frontier edge each step.
+pub(super) struct Prim;
+
+impl MazeGenerator for Prim {
+    fn carve(&self, grid: &mut Grid, rng: &mut Rng) -> usize {
+        let mut in_maze = vec![false; grid.cell_count()];
+        let start = rng.below(grid.cell_count());
+        in_maze[start] = true;
+
+        let mut frontier: Vec<(usize, usize)> = grid
+            .neighbours(start)
+            .into_iter()
+            .map(|(neighbour, _, _)| (start, neighbour))
+            .collect();
+
+        while !frontier.is_empty() {
+            let (cell, next) = frontier.swap_remove(rng.below(frontier.len()));
+            if in_maze[next] {
+                continue;
+            }
+            grid.carve(cell, next);
+            in_maze[next] = true;
+            frontier.extend(
+                grid.neighbours(next)
+                    .into_iter()
+                    .filter(|&(neighbour, _, _)| !in_maze[neighbour])
+                    .map(|(neighbour, _, _)| (next, neighbour)),
+            );
+        }
+        start
+    }
+}
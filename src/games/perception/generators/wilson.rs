@@ -0,0 +1,37 @@
+use super::{Grid, MazeGenerator, Rng};
+
+/// Wilson's: loop-erased random walks from each cell until they hit the maze,
+/// which samples uniformly from all spanning trees.
+pub(super) struct Wilson;
+
+impl MazeGenerator for Wilson {
+    fn carve(&self, grid: &mut Grid, rng: &mut Rng) -> usize {
+        let cell_count = grid.cell_count();
+        let mut in_maze = vec![false; cell_count];
+        let root = rng.below(cell_count);
+        in_maze[root] = true;
+
+        let mut order: Vec<usize> = (0..cell_count).collect();
+        rng.shuffle(&mut order);
+
+        // Direction the latest walk left each cell by; revisiting a cell
+        // overwrites it, which erases the loop.
+        let mut next = vec![0; cell_count];
+        for walk_start in order {
+            let mut cell = walk_start;
+            while !in_maze[cell] {
+                let neighbours = grid.neighbours(cell);
+                next[cell] = neighbours[rng.below(neighbours.len())].0;
+                cell = next[cell];
+            }
+
+            let mut cell = walk_start;
+            while !in_maze[cell] {
+                in_maze[cell] = true;
+                grid.carve(cell, next[cell]);
+                cell = next[cell];
+            }
+        }
+        root
+    }
+}
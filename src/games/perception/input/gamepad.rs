@@ -0,0 +1,80 @@
+use super::{InputSource, MoveIntent, Perception};
+use std::{cell::RefCell, rc::Rc};
+use wasm_bindgen::prelude::*;
+
+// How often connected gamepads are read, in milliseconds
+const POLL_INTERVAL: i32 = 50;
+// Delay between repeated moves while a direction is held, in milliseconds
+const REPEAT_DELAY: f64 = 250.0;
+
+// D-pad buttons in the standard mapping, with their screen direction
+const DPAD: [(u32, (f64, f64)); 4] = [
+    (12, (0.0, -1.0)),
+    (13, (0.0, 1.0)),
+    (14, (-1.0, 0.0)),
+    (15, (1.0, 0.0)),
+];
+
+/// Browser Gamepad API, polled: the D-pad or left stick steers.
+pub(super) struct Gamepad;
+
+/// Direction held on any connected pad, D-pad first, then the left stick
+/// outside the dead zone.
+fn held_direction(dead_zone: f64) -> Option<(f64, f64)> {
+    let pads = web_sys::window()?.navigator().get_gamepads().ok()?;
+    pads.iter()
+        .filter_map(|pad| pad.dyn_into::<web_sys::Gamepad>().ok())
+        .filter(|pad| pad.connected())
+        .find_map(|pad| {
+            let buttons = pad.buttons();
+            let (dx, dy) = DPAD
+                .iter()
+                .filter(|&&(button, _)| {
+                    buttons
+                        .get(button)
+                        .dyn_into::<web_sys::GamepadButton>()
+                        .is_ok_and(|button| button.pressed())
+                })
+                .fold((0.0, 0.0), |(x, y), &(_, (dx, dy))| (x + dx, y + dy));
+            if dx != 0.0 || dy != 0.0 {
+                return Some((dx, dy));
+            }
+
+            let axes = pad.axes();
+            let x = axes.get(0).as_f64().unwrap_or(0.0);
+            let y = axes.get(1).as_f64().unwrap_or(0.0);
+            (x.hypot(y) > dead_zone).then_some((x, y))
+        })
+}
+
+impl InputSource for Gamepad {
+    fn attach(&self, game_state: Rc<RefCell<Perception>>) -> Result<(), JsValue> {
+        let window = web_sys::window().unwrap();
+        let performance = window.performance().unwrap();
+        // When the held direction last moved the player
+        let mut last_move: Option<f64> = None;
+
+        let poll = Closure::wrap(Box::new(move || {
+            let Ok(mut game) = game_state.try_borrow_mut() else {
+                return;
+            };
+            match held_direction(game.settings.gamepad_dead_zone) {
+                None => last_move = None,
+                Some((dx, dy)) => {
+                    let now = performance.now();
+                    if last_move.is_none_or(|at| now - at >= REPEAT_DELAY) {
+                        last_move = Some(now);
+                        game.apply_input(MoveIntent::Towards(dx, dy)).unwrap();
+                    }
+                }
+            }
+        }) as Box<dyn FnMut()>);
+
+        window.set_interval_with_callback_and_timeout_and_arguments_0(
+            poll.as_ref().unchecked_ref(),
+            POLL_INTERVAL,
+        )?;
+        poll.forget();
+        Ok(())
+    }
+}
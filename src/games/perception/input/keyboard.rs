@@ -0,0 +1,42 @@
+use super::{InputSource, MoveIntent, MoveResult, Perception};
+use std::{cell::RefCell, rc::Rc};
+use wasm_bindgen::prelude::*;
+
+/// Keys bound to directions in the player's settings.
+pub(super) struct Keyboard;
+
+impl InputSource for Keyboard {
+    fn attach(&self, game_state: Rc<RefCell<Perception>>) -> Result<(), JsValue> {
+        let document = game_state.borrow().page()?;
+        let key_handler = Closure::wrap(Box::new(move |event: web_sys::KeyboardEvent| {
+            if event.repeat() || event.ctrl_key() || event.meta_key() || event.alt_key() {
+                return;
+            }
+            if let Ok(mut game) = game_state.try_borrow_mut() {
+                if game.press_key(&event.code()).unwrap().is_some() {
+                    // Keep arrow keys from scrolling the page.
+                    event.prevent_default();
+                }
+            }
+        }) as Box<dyn FnMut(_)>);
+
+        document
+            .add_event_listener_with_callback("keydown", key_handler.as_ref().unchecked_ref())?;
+        key_handler.forget();
+        Ok(())
+    }
+}
+
+impl Perception {
+    /// Moves the way the key with `code` is bound to. None if it is unbound.
+    pub(in crate::games::perception) fn press_key(
+        &mut self,
+        code: &str,
+    ) -> Result<Option<MoveResult>, JsValue> {
+        let Some(&direction) = self.settings.key_bindings.get(code) else {
+            return Ok(None);
+        };
+        let (dx, dy) = direction.vector();
+        self.apply_input(MoveIntent::Towards(dx, dy)).map(Some)
+    }
+}
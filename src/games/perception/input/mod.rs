@@ -0,0 +1,61 @@
+mod gamepad;
+mod keyboard;
+mod pointer;
+mod swipe;
+
+use super::{board::MoveResult, Perception};
+use std::{cell::RefCell, rc::Rc};
+use wasm_bindgen::prelude::*;
+
+/// What an input device asks for: a particular cell, or whichever neighbour
+/// lies in a direction (screen axes, y down).
+#[derive(Clone, Copy, Debug)]
+pub(super) enum MoveIntent {
+    Cell(usize, usize),
+    Towards(f64, f64),
+}
+
+/// A device the maze can be played with. Each source hooks itself up to the
+/// shared game and feeds moves through `Perception::apply_input`.
+pub(super) trait InputSource {
+    fn attach(&self, game_state: Rc<RefCell<Perception>>) -> Result<(), JsValue>;
+}
+
+/// Every input source, in attach order. Swipes come before clicks so the
+/// click that ends a swipe can be dropped.
+pub(super) fn sources() -> [&'static dyn InputSource; 4] {
+    [
+        &swipe::Swipe,
+        &pointer::Pointer,
+        &keyboard::Keyboard,
+        &gamepad::Gamepad,
+    ]
+}
+
+impl Perception {
+    /// Applies a move from any input source, redrawing if anything changed.
+    /// Input is ignored while a replay is showing or the game is paused.
+    pub(super) fn apply_input(&mut self, intent: MoveIntent) -> Result<MoveResult, JsValue> {
+        // The maze on screen during a replay is not the one being played,
+        // and a paused one is not on screen at all
+        if self.replay.is_some() || self.paused_at.is_some() {
+            return Ok(MoveResult::NotAdjacent);
+        }
+        let target = match intent {
+            MoveIntent::Cell(x, y) => Some((x, y)),
+            MoveIntent::Towards(dx, dy) => self
+                .board
+                .maze
+                .neighbour_towards(self.board.current_position, (dx, dy)),
+        };
+        let Some((x, y)) = target else {
+            return Ok(MoveResult::NotAdjacent);
+        };
+
+        let result = self.try_move(x, y);
+        if result.changed() {
+            self.render()?;
+        }
+        Ok(result)
+    }
+}
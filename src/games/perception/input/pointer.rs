@@ -0,0 +1,74 @@
+use super::{InputSource, MoveIntent, Perception};
+use std::{cell::RefCell, rc::Rc};
+use wasm_bindgen::prelude::*;
+use web_sys::Element;
+
+/// Mouse clicks and taps on a cell, drawn as an element or on the canvas.
+pub(super) struct Pointer;
+
+impl InputSource for Pointer {
+    fn attach(&self, game_state: Rc<RefCell<Perception>>) -> Result<(), JsValue> {
+        let click_handler = {
+            let game_state = game_state.clone();
+            Closure::wrap(Box::new(move |event: web_sys::MouseEvent| {
+                if let Ok(mut game) = game_state.try_borrow_mut() {
+                    if let Some(target) = event.target() {
+                        if let Some(element) = target.dyn_ref::<Element>() {
+                            if let Ok(Some(maze_el)) = element.closest("#maze") {
+                                // Find clicked cell index
+                                let children = maze_el.children();
+                                // Clicks between cells land on the maze itself.
+                                let Some(cell_index) = (0..children.length()).find(|&i| {
+                                    children
+                                        .item(i)
+                                        .is_some_and(|cell| cell.is_same_node(Some(element)))
+                                }) else {
+                                    return;
+                                };
+                                let cell_index = cell_index as usize;
+
+                                let width = game.board.maze.width;
+                                let x = cell_index % width;
+                                let y = cell_index / width;
+
+                                game.apply_input(MoveIntent::Cell(x, y)).unwrap();
+                            }
+                        }
+                    }
+                }
+            }) as Box<dyn FnMut(_)>)
+        };
+
+        // The canvas has no cell elements, so clicks are hit-tested instead
+        let canvas_handler = {
+            let game_state = game_state.clone();
+            Closure::wrap(Box::new(move |event: web_sys::MouseEvent| {
+                if let Ok(mut game) = game_state.try_borrow_mut() {
+                    let (x, y) = (f64::from(event.offset_x()), f64::from(event.offset_y()));
+                    if let Some(cell) = game.canvas_cell_at(x, y) {
+                        game.apply_input(MoveIntent::Cell(cell.0, cell.1)).unwrap();
+                    }
+                }
+            }) as Box<dyn FnMut(_)>)
+        };
+
+        let document = game_state.borrow().page()?;
+        // Attach single click handler to maze container
+        if let Some(maze_el) = document.get_element_by_id("maze") {
+            maze_el.add_event_listener_with_callback(
+                "click",
+                click_handler.as_ref().unchecked_ref(),
+            )?;
+            click_handler.forget();
+        }
+        if let Some(canvas) = document.get_element_by_id("maze-canvas") {
+            canvas.add_event_listener_with_callback(
+                "click",
+                canvas_handler.as_ref().unchecked_ref(),
+            )?;
+            canvas_handler.forget();
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+use super::{InputSource, MoveIntent, Perception};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+use wasm_bindgen::prelude::*;
+use web_sys::PointerEvent;
+
+/// Drags across the maze, moving towards the drag direction.
+pub(super) struct Swipe;
+
+impl InputSource for Swipe {
+    fn attach(&self, game_state: Rc<RefCell<Perception>>) -> Result<(), JsValue> {
+        let document = game_state.borrow().page()?;
+        // Swipes work the same on either renderer's surface
+        let surfaces: Vec<_> = ["maze", "maze-canvas"]
+            .iter()
+            .filter_map(|id| document.get_element_by_id(id))
+            .collect();
+        // Where and when the current drag started
+        let origin: Rc<Cell<Option<(f64, f64, f64)>>> = Rc::new(Cell::new(None));
+        let swiped = Rc::new(Cell::new(false));
+
+        let down_handler = {
+            let origin = origin.clone();
+            let swiped = swiped.clone();
+            Closure::wrap(Box::new(move |event: PointerEvent| {
+                // Touch drags fire no click, so the last swipe's flag may linger
+                swiped.set(false);
+                if event.is_primary() {
+                    origin.set(Some((
+                        f64::from(event.client_x()),
+                        f64::from(event.client_y()),
+                        event.time_stamp(),
+                    )));
+                }
+            }) as Box<dyn FnMut(_)>)
+        };
+
+        let up_handler = {
+            let origin = origin.clone();
+            let swiped = swiped.clone();
+            Closure::wrap(Box::new(move |event: PointerEvent| {
+                let Some((x, y, start)) = origin.take() else {
+                    return;
+                };
+                if let Ok(mut game) = game_state.try_borrow_mut() {
+                    let dx = f64::from(event.client_x()) - x;
+                    let dy = f64::from(event.client_y()) - y;
+                    if dx.hypot(dy) < game.settings.swipe_distance
+                        || event.time_stamp() - start > game.settings.swipe_duration
+                    {
+                        return;
+                    }
+                    swiped.set(true);
+                    game.apply_input(MoveIntent::Towards(dx, dy)).unwrap();
+                }
+            }) as Box<dyn FnMut(_)>)
+        };
+
+        let cancel_handler = Closure::wrap(Box::new(move |_event: PointerEvent| {
+            origin.set(None);
+        }) as Box<dyn FnMut(_)>);
+
+        // A swipe that ends over another cell still fires a click; swallow it.
+        let click_guard = Closure::wrap(Box::new(move |event: web_sys::MouseEvent| {
+            if swiped.replace(false) {
+                event.stop_immediate_propagation();
+            }
+        }) as Box<dyn FnMut(_)>);
+
+        for surface in &surfaces {
+            surface.add_event_listener_with_callback(
+                "pointerdown",
+                down_handler.as_ref().unchecked_ref(),
+            )?;
+            surface.add_event_listener_with_callback(
+                "pointerup",
+                up_handler.as_ref().unchecked_ref(),
+            )?;
+            surface.add_event_listener_with_callback(
+                "pointercancel",
+                cancel_handler.as_ref().unchecked_ref(),
+            )?;
+            surface
+                .add_event_listener_with_callback("click", click_guard.as_ref().unchecked_ref())?;
+        }
+        down_handler.forget();
+        up_handler.forget();
+        cancel_handler.forget();
+        click_guard.forget();
+        Ok(())
+    }
+}
@@ -1,166 +1,646 @@
-use crate::Perception;
-use js_sys::Date;
-use js_sys::Math;
-use std::collections::{HashSet, VecDeque};
-use web_sys::Document;
-
-impl Perception {
-    pub(super) fn create_maze(size: usize, document: Document) -> Self {
-        // Total cells and walls per cell (top, right, bottom, left)
-        let total_cells = size * size;
-        let wall_per_cell = 4;
-        let mut walls = vec![true; total_cells * wall_per_cell];
-
-        // DFS setup for spanning tree generation
-        let mut visited_cells = vec![false; total_cells];
-        let idx = |r: usize, c: usize| r * size + c; // utility: (row, col) -> index
-
-        // Pick a random starting cell (row, col)
-        let start_row = (Math::random() * size as f64).floor() as usize;
-        let start_col = (Math::random() * size as f64).floor() as usize;
-
-        let mut stack = vec![(start_row, start_col)];
-        visited_cells[idx(start_row, start_col)] = true;
-
-        // Directions: (dr, dc, current wall index, neighbor wall index)
-        // Up: (r-1, c) uses wall 0 in current and 2 in neighbor.
-        // Right: (r, c+1) uses wall 1 in current and 3 in neighbor.
-        // Down: (r+1, c) uses wall 2 in current and 0 in neighbor.
-        // Left: (r, c-1) uses wall 3 in current and 1 in neighbor.
-        let directions = [
-            (-1, 0, 0, 2),
-            (0, 1, 1, 3),
-            (1, 0, 2, 0),
-            (0, -1, 3, 1),
-        ];
-
-        // Iterative DFS: remove walls to create a spanning tree
-        while let Some((r, c)) = stack.last().copied() {
-            let mut neighbors = Vec::new();
-            for &(dr, dc, cur_wall, nb_wall) in &directions {
-                let nr = r as isize + dr;
-                let nc = c as isize + dc;
-                if nr >= 0 && nr < size as isize && nc >= 0 && nc < size as isize {
-                    let nr = nr as usize;
-                    let nc = nc as usize;
-                    if !visited_cells[idx(nr, nc)] {
-                        neighbors.push((nr, nc, cur_wall, nb_wall));
-                    }
+use super::{
+    code::{self, CodeError},
+    generators::{self, Algorithm},
+    solver,
+    topology::Topology,
+};
+use crate::games::rng::Rng;
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, convert::TryFrom};
+
+/// Most key/door pairs a maze can hold, one per colour.
+pub(super) const MAX_KEYS: usize = 5;
+/// Longest shorter side a level's maze may have.
+pub(super) const MAX_SIZE: usize = 256;
+/// Most a maze's longer side may be, as a multiple of its shorter one.
+pub(super) const MAX_ASPECT: f64 = 4.0;
+
+/// Cell graph that generators carve passages into. Cells are indexed row-major
+/// and every wall starts up.
+pub(super) struct Grid {
+    topology: Topology,
+    width: usize,
+    height: usize,
+    walls: Vec<bool>,
+}
+
+impl Grid {
+    pub(super) fn new(topology: Topology, width: usize, height: usize) -> Self {
+        Self {
+            topology,
+            width,
+            height,
+            walls: vec![true; width * height * topology.cells().walls_per_cell()],
+        }
+    }
+
+    pub(super) fn cell_count(&self) -> usize {
+        self.width * self.height
+    }
+
+    pub(super) fn rows(&self) -> usize {
+        self.height
+    }
+
+    pub(super) fn row(&self, cell: usize) -> usize {
+        cell / self.width
+    }
+
+    /// Cells in row `row`, left to right.
+    pub(super) fn row_cells(&self, row: usize) -> std::ops::Range<usize> {
+        row * self.width..(row + 1) * self.width
+    }
+
+    /// Neighbours of `cell` as `(neighbour, wall in cell, wall in neighbour)`,
+    /// in wall order.
+    pub(super) fn neighbours(&self, cell: usize) -> Vec<(usize, usize, usize)> {
+        neighbours(self.topology, self.width, self.height, cell)
+    }
+
+    /// Neighbours reachable from `cell` without crossing a wall.
+    pub(super) fn open_neighbours(&self, cell: usize) -> Vec<usize> {
+        let walls_per_cell = self.topology.cells().walls_per_cell();
+        self.neighbours(cell)
+            .into_iter()
+            .filter(|&(_, wall, _)| !self.walls[cell * walls_per_cell + wall])
+            .map(|(neighbour, _, _)| neighbour)
+            .collect()
+    }
+
+    /// Removes the wall between two adjacent cells.
+    pub(super) fn carve(&mut self, cell: usize, neighbour: usize) {
+        if let Some((_, wall, nb_wall)) = self
+            .neighbours(cell)
+            .into_iter()
+            .find(|&(n, _, _)| n == neighbour)
+        {
+            let walls_per_cell = self.topology.cells().walls_per_cell();
+            self.walls[cell * walls_per_cell + wall] = false;
+            self.walls[neighbour * walls_per_cell + nb_wall] = false;
+        }
+    }
+
+    pub(super) fn position(&self, cell: usize) -> (usize, usize) {
+        (cell % self.width, cell / self.width)
+    }
+
+    pub(super) fn cell(&self, (x, y): (usize, usize)) -> usize {
+        y * self.width + x
+    }
+
+    /// BFS over open passages: distance from `start` and the parent of each
+    /// reached cell.
+    fn distances(&self, start: usize) -> (Vec<Option<usize>>, Vec<Option<usize>>) {
+        let mut dist = vec![None; self.cell_count()];
+        let mut parent = vec![None; self.cell_count()];
+        let mut queue = VecDeque::new();
+        dist[start] = Some(0);
+        queue.push_back(start);
+
+        while let Some(current) = queue.pop_front() {
+            for neighbour in self.open_neighbours(current) {
+                if dist[neighbour].is_none() {
+                    dist[neighbour] = Some(dist[current].unwrap() + 1);
+                    parent[neighbour] = Some(current);
+                    queue.push_back(neighbour);
                 }
             }
+        }
+        (dist, parent)
+    }
 
-            if !neighbors.is_empty() {
-                // Shuffle neighbors using js_sys::Math::random
-                neighbors.sort_by(|_, _| {
-                    if Math::random() < 0.5 {
-                        std::cmp::Ordering::Less
-                    } else {
-                        std::cmp::Ordering::Greater
-                    }
-                });
-                let (nr, nc, cur_wall, nb_wall) = neighbors[0];
-                // Remove walls between current and neighbor
-                let cell_base = idx(r, c) * wall_per_cell;
-                walls[cell_base + cur_wall] = false;
-                let nb_base = idx(nr, nc) * wall_per_cell;
-                walls[nb_base + nb_wall] = false;
-                visited_cells[idx(nr, nc)] = true;
-                stack.push((nr, nc));
+    /// The reached cell furthest from `start`, with the BFS parent pointers.
+    fn furthest(&self, start: usize) -> (usize, Vec<Option<usize>>) {
+        let (dist, parent) = self.distances(start);
+        let (furthest, _) = dist
+            .iter()
+            .enumerate()
+            .filter_map(|(i, d)| d.map(|d| (i, d)))
+            .max_by_key(|&(_, d)| d)
+            .unwrap();
+        (furthest, parent)
+    }
+}
+
+/// Neighbours of `cell` in a `width` × `height` grid of `topology`, as
+/// `(neighbour, wall in cell, wall in neighbour)` in wall order.
+fn neighbours(
+    topology: Topology,
+    width: usize,
+    height: usize,
+    cell: usize,
+) -> Vec<(usize, usize, usize)> {
+    let cells = topology.cells();
+    let (x, y) = ((cell % width) as isize, (cell / width) as isize);
+    (0..cells.walls_per_cell())
+        .filter_map(|wall| {
+            let ((nx, ny), nb_wall) = cells.across(x, y, wall);
+            if nx >= 0 && nx < width as isize && ny >= 0 && ny < height as isize {
+                Some((ny as usize * width + nx as usize, wall, nb_wall))
             } else {
-                stack.pop();
+                None
             }
+        })
+        .collect()
+}
+
+/// Generation settings, carried over from one maze to the next.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(super) struct MazeOptions {
+    #[serde(default)]
+    pub(super) algorithm: Algorithm,
+    // Chance in [0, 1] of opening each dead end into a loop; 0 keeps the maze perfect
+    #[serde(default)]
+    pub(super) braid: f64,
+    // Key/door pairs, up to MAX_KEYS; fewer are placed if the maze is too small
+    #[serde(default = "default_keys")]
+    pub(super) keys: usize,
+    // Whether keys only pick up in order
+    #[serde(default)]
+    pub(super) ordered_keys: bool,
+    // Width to height ratio; above 1 is landscape, below 1 portrait
+    #[serde(default = "default_aspect")]
+    pub(super) aspect: f64,
+    #[serde(default)]
+    pub(super) topology: Topology,
+}
+
+fn default_keys() -> usize {
+    1
+}
+
+fn default_aspect() -> f64 {
+    1.0
+}
+
+impl Default for MazeOptions {
+    fn default() -> Self {
+        Self {
+            algorithm: Algorithm::default(),
+            braid: 0.0,
+            keys: default_keys(),
+            ordered_keys: false,
+            aspect: default_aspect(),
+            topology: Topology::default(),
+        }
+    }
+}
+
+impl MazeOptions {
+    /// Checks options read from a save.
+    pub(super) fn validate(&self) -> Result<(), &'static str> {
+        if !(self.aspect.is_finite() && self.aspect > 0.0) {
+            return Err("aspect ratio is not positive");
+        }
+        if !(0.0..=1.0).contains(&self.braid) {
+            return Err("braid factor is outside 0 to 1");
         }
+        Ok(())
+    }
 
-        // --- BFS helper: find furthest cell (and parent pointers) ---
-        let bfs = |start_cell: (usize, usize)| {
-            let mut dist = vec![None; total_cells];
-            let mut parent = vec![None; total_cells];
-            let start_idx = idx(start_cell.0, start_cell.1);
-            let mut queue = VecDeque::new();
-            dist[start_idx] = Some(0);
-            queue.push_back(start_idx);
-
-            while let Some(current) = queue.pop_front() {
-                let r = current / size;
-                let c = current % size;
-                for &(dr, dc, cur_wall, _) in &directions {
-                    let nr = r as isize + dr;
-                    let nc = c as isize + dc;
-                    if nr >= 0 && nr < size as isize && nc >= 0 && nc < size as isize {
-                        let nr = nr as usize;
-                        let nc = nc as usize;
-                        let neighbor_idx = idx(nr, nc);
-                        // Only move if there is no wall between current and neighbor.
-                        let cell_base = current * wall_per_cell;
-                        if !walls[cell_base + cur_wall] && dist[neighbor_idx].is_none() {
-                            dist[neighbor_idx] = Some(dist[current].unwrap() + 1);
-                            parent[neighbor_idx] = Some(current);
-                            queue.push_back(neighbor_idx);
-                        }
-                    }
-                }
-            }
+    /// Width and height of a maze whose shorter side is `size`.
+    pub(super) fn dimensions(&self, size: usize) -> (usize, usize) {
+        if self.aspect >= 1.0 {
+            ((size as f64 * self.aspect).round() as usize, size)
+        } else {
+            (size, (size as f64 / self.aspect).round() as usize)
+        }
+    }
+}
+
+/// A generated maze layout. Positions are `(x, y)` with `x` the column.
+/// Saved as a `SavedMaze`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(into = "SavedMaze", try_from = "SavedMaze")]
+pub(super) struct Maze {
+    pub(super) width: usize,
+    pub(super) height: usize,
+    // Seed the maze was generated from
+    pub(super) seed: u32,
+    // Options the maze was generated with
+    pub(super) options: MazeOptions,
+    pub(super) walls: Vec<bool>,
+    pub(super) start_position: (usize, usize),
+    // Key `i` opens door `i`; the last door is the exit
+    pub(super) keys: Vec<(usize, usize)>,
+    pub(super) doors: Vec<(usize, usize)>,
+    // Moves in the optimal route from the start through the exit
+    pub(super) par: usize,
+}
+
+/// A maze as saves hold it: its share code, a few bytes where its walls as
+/// JSON run to megabytes on the largest mazes, and the options codes leave
+/// out.
+#[derive(Serialize, Deserialize)]
+struct SavedMaze {
+    code: String,
+    options: MazeOptions,
+}
+
+impl From<Maze> for SavedMaze {
+    fn from(maze: Maze) -> Self {
+        Self {
+            code: code::encode(&maze),
+            options: maze.options,
+        }
+    }
+}
 
-            // Find the furthest cell from the start
-            let (furthest_idx, _) = dist
-                .iter()
-                .enumerate()
-                .filter_map(|(i, d)| d.map(|d| (i, d)))
-                .max_by_key(|&(_, d)| d)
-                .unwrap();
-            ((furthest_idx / size, furthest_idx % size), parent)
+impl TryFrom<SavedMaze> for Maze {
+    type Error = CodeError;
+
+    fn try_from(saved: SavedMaze) -> Result<Self, CodeError> {
+        let mut maze = code::decode(&saved.code)?;
+        // The walls are laid out for the code's cell shape, whatever the
+        // options say
+        maze.options = MazeOptions {
+            topology: maze.options.topology,
+            ..saved.options
         };
+        Ok(maze)
+    }
+}
+
+impl Maze {
+    pub(super) fn generate(width: usize, height: usize, seed: u32, options: MazeOptions) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut grid = Grid::new(options.topology, width, height);
+        let origin = options.algorithm.generator().carve(&mut grid, &mut rng);
+        if options.braid > 0.0 {
+            generators::braid(&mut grid, options.braid, &mut rng);
+        }
 
-        // --- Determine maze endpoints using the diameter ---
-        let (cell_a, _) = bfs((start_row, start_col));
-        let (cell_b, parent_map) = bfs(cell_a);
+        // --- Determine maze endpoints using the (approximate, once braided) diameter ---
+        let (cell_a, _) = grid.furthest(origin);
+        let (cell_b, parent_map) = grid.furthest(cell_a);
 
-        // Reconstruct the unique path (from cell_a to cell_b)
-        let mut path = Vec::new();
-        let mut current = idx(cell_b.0, cell_b.1);
-        path.push(current);
+        // Reconstruct a shortest path (from cell_a to cell_b)
+        let mut path = vec![cell_b];
+        let mut current = cell_b;
         while let Some(p) = parent_map[current] {
             path.push(p);
             current = p;
         }
         path.reverse();
 
-        // In the DFS/BFS we used (row, col) order.
-        // Convert to (x, y) where x = col and y = row to match movement.rs.
-        let convert = |(r, c): (usize, usize)| (c, r);
-        let start_rc = cell_a;
-        let door_rc = cell_b;
-        let key_rc = if path.len() >= 3 {
-            // Pick a random intermediate index (excluding endpoints)
-            let key_idx = 1 + ((Math::random() * ((path.len() - 2) as f64)).floor() as usize);
-            let cell = path[key_idx];
-            (cell / size, cell % size)
+        // Along the path: key 0, door 0, key 1, door 1, …, last key, exit.
+        // Every key lies before its door, so following the path always solves it.
+        let (keys, mut doors) = if path.len() >= 3 {
+            let count = options.keys.clamp(1, MAX_KEYS).min((path.len() - 1) / 2);
+            // Pick random intermediate indices (excluding endpoints)
+            let mut slots: Vec<usize> = (1..path.len() - 1).collect();
+            let mut picked: Vec<usize> = (0..2 * count - 1)
+                .map(|_| slots.remove(rng.below(slots.len())))
+                .collect();
+            picked.sort_unstable();
+            let cells = |parity| {
+                picked
+                    .iter()
+                    .skip(parity)
+                    .step_by(2)
+                    .map(|&i| grid.position(path[i]))
+                    .collect::<Vec<_>>()
+            };
+            (cells(0), cells(1))
         } else {
-            start_rc
+            (vec![grid.position(cell_a)], Vec::new())
         };
+        doors.push(grid.position(cell_b));
 
-        let start_cell = convert(start_rc);
-        let door_cell = convert(door_rc);
-        let key_cell  = convert(key_rc);
+        let mut maze = Self {
+            width,
+            height,
+            seed,
+            options,
+            start_position: grid.position(cell_a),
+            keys,
+            doors,
+            walls: grid.walls,
+            par: 0,
+        };
+        maze.par = solver::par(&maze);
+        maze
+    }
 
-        // --- Initialize remaining fields ---
-        let mut visited = HashSet::new();
-        visited.insert(start_cell);
+    pub(super) fn grid(&self) -> Grid {
+        Grid {
+            topology: self.options.topology,
+            width: self.width,
+            height: self.height,
+            walls: self.walls.clone(),
+        }
+    }
 
-        Self {
-            document,
-            size,
-            walls,
-            current_position: start_cell,
-            start_position: start_cell,
-            key_position: key_cell,
-            door_position: door_cell,
-            visited,
-            has_key: false,
-            level: 1,
-            moves: 0,
-            time_remaining: 300,
-            last_tick: Date::now() / 1000.0,
+    /// Whether a position lies inside the maze.
+    pub(super) fn contains(&self, (x, y): (usize, usize)) -> bool {
+        x < self.width && y < self.height
+    }
+
+    /// Checks a maze read from a save: walls that fit its size and items
+    /// inside it. Decoding its code has already found it a way out.
+    pub(super) fn validate(&self) -> Result<(), &'static str> {
+        self.options.validate()?;
+        if self.width == 0 || self.height == 0 {
+            return Err("maze has no cells");
+        }
+        let walls_per_cell = self.options.topology.cells().walls_per_cell();
+        if self.walls.len() != self.width * self.height * walls_per_cell {
+            return Err("wall count does not match the maze's size");
+        }
+        if self.keys.is_empty() || self.keys.len() > MAX_KEYS || self.keys.len() != self.doors.len()
+        {
+            return Err("keys and doors do not pair up");
+        }
+        let mut items = self.keys.iter().chain(&self.doors);
+        if !self.contains(self.start_position) || !items.all(|&item| self.contains(item)) {
+            return Err("start, key or door lies outside the maze");
+        }
+        Ok(())
+    }
+
+    /// Row-major index of a position, matching the order cells are drawn in.
+    pub(super) fn cell_index(&self, (x, y): (usize, usize)) -> usize {
+        y * self.width + x
+    }
+
+    /// The wall of `from` that faces `to`, if the two cells are adjacent.
+    pub(super) fn wall_between(&self, from: (usize, usize), to: (usize, usize)) -> Option<usize> {
+        let to = self.cell_index(to);
+        neighbours(
+            self.options.topology,
+            self.width,
+            self.height,
+            self.cell_index(from),
+        )
+        .into_iter()
+        .find(|&(neighbour, _, _)| neighbour == to)
+        .map(|(_, wall, _)| wall)
+    }
+
+    /// Centre of a cell, in the topology's drawing units.
+    pub(super) fn centre(&self, (x, y): (usize, usize)) -> (f64, f64) {
+        let corners = self.options.topology.cells().corners(x, y);
+        let n = corners.len() as f64;
+        let (sx, sy) = corners
+            .iter()
+            .fold((0.0, 0.0), |(sx, sy), (cx, cy)| (sx + cx, sy + cy));
+        (sx / n, sy / n)
+    }
+
+    /// The neighbour of `from` lying closest to the direction `(dx, dy)`
+    /// (screen axes, y down), walls or not. None if nothing lies within about
+    /// 60° of it.
+    pub(super) fn neighbour_towards(
+        &self,
+        from: (usize, usize),
+        (dx, dy): (f64, f64),
+    ) -> Option<(usize, usize)> {
+        let length = dx.hypot(dy);
+        if length == 0.0 {
+            return None;
+        }
+        let (fx, fy) = self.centre(from);
+        neighbours(
+            self.options.topology,
+            self.width,
+            self.height,
+            self.cell_index(from),
+        )
+        .into_iter()
+        .map(|(neighbour, _, _)| {
+            let position = (neighbour % self.width, neighbour / self.width);
+            let (nx, ny) = self.centre(position);
+            let cosine = ((nx - fx) * dx + (ny - fy) * dy) / ((nx - fx).hypot(ny - fy) * length);
+            (position, cosine)
+        })
+        .filter(|&(_, cosine)| cosine > 0.45)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(position, _)| position)
+    }
+
+    pub(super) fn is_adjacent(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        self.contains(to) && self.wall_between(from, to).is_some()
+    }
+
+    /// Whether a wall separates two adjacent cells.
+    pub(super) fn has_wall(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        let walls_per_cell = self.options.topology.cells().walls_per_cell();
+        self.wall_between(from, to)
+            .is_some_and(|wall| self.walls[self.cell_index(from) * walls_per_cell + wall])
+    }
+
+    pub(super) fn key_at(&self, position: (usize, usize)) -> Option<usize> {
+        self.keys.iter().position(|&key| key == position)
+    }
+
+    pub(super) fn door_at(&self, position: (usize, usize)) -> Option<usize> {
+        self.doors.iter().position(|&door| door == position)
+    }
+
+    /// Index of the exit, the last door.
+    pub(super) fn exit(&self) -> usize {
+        self.doors.len() - 1
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use crate::games::perception::{
+        code::{self, tests::assert_same_layout},
+        drawing::{ascii, Overlay},
+        generators::tests::{is_spanning_tree, ALGORITHMS, TOPOLOGIES},
+    };
+
+    const BRAIDS: [f64; 3] = [0.0, 0.4, 1.0];
+
+    /// Every algorithm, topology and key count, with and without ordered
+    /// keys, at a spread of braid factors.
+    fn every_option() -> Vec<MazeOptions> {
+        let mut every = Vec::new();
+        for algorithm in ALGORITHMS {
+            for topology in TOPOLOGIES {
+                for keys in 1..=MAX_KEYS {
+                    for braid in BRAIDS {
+                        for ordered_keys in [false, true] {
+                            every.push(MazeOptions {
+                                algorithm,
+                                braid,
+                                keys,
+                                ordered_keys,
+                                topology,
+                                ..MazeOptions::default()
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        every
+    }
+
+    /// A few mazes for each of `every_option`, big enough to hold every key.
+    pub(in crate::games::perception) fn sample_mazes() -> impl Iterator<Item = Maze> {
+        every_option()
+            .into_iter()
+            .flat_map(|options| (0..3).map(move |seed| Maze::generate(11, 8, seed, options)))
+    }
+
+    #[test]
+    fn a_seed_pins_the_exact_layout() {
+        let maze = Maze::generate(6, 4, 42, MazeOptions::default());
+        assert_eq!(
+            ascii(&maze, Overlay::default()).unwrap(),
+            concat!(
+                "+---+---+---+---+---+---+\n",
+                "|               |       |\n",
+                "+   +   +---+---+   +   +\n",
+                "|   |               |   |\n",
+                "+   +---+---+---+---+   +\n",
+                "|   |   |       |       |\n",
+                "+   +   +---+   +   +   +\n",
+                "|           |       |   |\n",
+                "+---+---+---+---+---+---+\n",
+            )
+        );
+    }
+
+    #[test]
+    fn a_seed_pins_where_the_items_go() {
+        let options = MazeOptions {
+            algorithm: Algorithm::Prim,
+            keys: 2,
+            ..MazeOptions::default()
+        };
+        let maze = Maze::generate(6, 4, 42, options);
+        let items = Overlay {
+            items: true,
+            ..Overlay::default()
+        };
+        assert_eq!(
+            ascii(&maze, items).unwrap(),
+            concat!(
+                "+---+---+---+---+---+---+\n",
+                "|         B | a       S |\n",
+                "+---+   +---+   +---+---+\n",
+                "|           |       |   |\n",
+                "+---+   +---+   +---+   +\n",
+                "|     b   A             |\n",
+                "+---+   +---+   +---+---+\n",
+                "|       |               |\n",
+                "+---+---+---+---+---+---+\n",
+            )
+        );
+    }
+
+    #[test]
+    fn the_same_seed_builds_the_same_maze() {
+        for maze in sample_mazes() {
+            assert_eq!(
+                Maze::generate(maze.width, maze.height, maze.seed, maze.options),
+                maze
+            );
+        }
+    }
+
+    #[test]
+    fn unbraided_mazes_are_spanning_trees() {
+        for maze in sample_mazes().filter(|maze| maze.options.braid == 0.0) {
+            assert!(is_spanning_tree(&maze.grid()), "{:?}", maze.options);
+        }
+    }
+
+    #[test]
+    fn mazes_hold_the_keys_asked_for_and_validate() {
+        for maze in sample_mazes() {
+            assert_eq!(maze.keys.len(), maze.options.keys, "{:?}", maze.options);
+            assert_eq!(maze.doors.len(), maze.keys.len());
+
+            let mut items = maze.keys.clone();
+            items.extend(&maze.doors);
+            items.push(maze.start_position);
+            let count = items.len();
+            items.sort_unstable();
+            items.dedup();
+            assert_eq!(items.len(), count, "items share a cell");
+
+            assert!(maze.par > 0);
+            assert_eq!(maze.validate(), Ok(()));
         }
     }
+
+    #[test]
+    fn mazes_survive_a_round_trip_through_a_code() {
+        for maze in sample_mazes() {
+            let decoded = code::decode(&code::encode(&maze)).unwrap();
+            assert_same_layout(&decoded, &maze);
+        }
+    }
+
+    fn maze_of(topology: Topology) -> Maze {
+        let options = MazeOptions {
+            topology,
+            ..MazeOptions::default()
+        };
+        Maze::generate(5, 5, 0, options)
+    }
+
+    #[test]
+    fn square_cells_step_to_the_side_faced() {
+        let maze = maze_of(Topology::Square);
+        let towards = |from, direction| maze.neighbour_towards(from, direction);
+        assert_eq!(towards((2, 2), (0.0, -1.0)), Some((2, 1)));
+        assert_eq!(towards((2, 2), (1.0, 0.0)), Some((3, 2)));
+        assert_eq!(towards((2, 2), (0.0, 1.0)), Some((2, 3)));
+        assert_eq!(towards((2, 2), (-1.0, 0.0)), Some((1, 2)));
+        // Up and to the right from a corner: only the right-hand cell is
+        // there, and it counts only while the direction is within about 60°
+        assert_eq!(towards((0, 0), (1.0, -1.5)), Some((1, 0)));
+        assert_eq!(towards((0, 0), (1.0, -2.5)), None);
+        assert_eq!(towards((0, 0), (0.0, -1.0)), None);
+        assert_eq!(towards((0, 0), (0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn hex_cells_step_to_the_side_faced() {
+        let maze = maze_of(Topology::Hex);
+        let towards = |from, direction| maze.neighbour_towards(from, direction);
+        let d = std::f64::consts::FRAC_1_SQRT_2;
+        // Row 1 is shifted half a cell right of rows 0 and 2
+        assert_eq!(towards((1, 1), (1.0, 0.0)), Some((2, 1)));
+        assert_eq!(towards((1, 1), (-1.0, 0.0)), Some((0, 1)));
+        assert_eq!(towards((1, 1), (d, -d)), Some((2, 0)));
+        assert_eq!(towards((1, 1), (-d, -d)), Some((1, 0)));
+        assert_eq!(towards((1, 1), (d, d)), Some((2, 2)));
+        assert_eq!(towards((1, 1), (-d, d)), Some((1, 2)));
+        assert_eq!(towards((2, 2), (d, -d)), Some((2, 1)));
+        assert_eq!(towards((2, 2), (-d, -d)), Some((1, 1)));
+        assert_eq!(towards((0, 0), (0.0, -1.0)), None);
+    }
+
+    #[test]
+    fn triangle_cells_step_to_the_side_faced() {
+        let maze = maze_of(Topology::Triangle);
+        let towards = |from, direction| maze.neighbour_towards(from, direction);
+        let d = std::f64::consts::FRAC_1_SQRT_2;
+        // (2, 2) points up, so its base is below; (1, 2) points down
+        assert_eq!(towards((2, 2), (0.0, 1.0)), Some((2, 3)));
+        assert_eq!(towards((2, 2), (-d, -d)), Some((1, 2)));
+        assert_eq!(towards((2, 2), (d, -d)), Some((3, 2)));
+        assert_eq!(towards((1, 2), (0.0, -1.0)), Some((1, 1)));
+        assert_eq!(towards((1, 2), (-d, d)), Some((0, 2)));
+        assert_eq!(towards((1, 2), (d, d)), Some((2, 2)));
+        assert_eq!(towards((0, 0), (-1.0, 0.0)), None);
+    }
+
+    #[test]
+    fn mazes_too_small_for_their_keys_hold_fewer() {
+        let options = MazeOptions {
+            keys: MAX_KEYS,
+            ..MazeOptions::default()
+        };
+        let maze = Maze::generate(2, 2, 1, options);
+        assert_eq!(maze.keys.len(), 1);
+        assert_eq!(maze.validate(), Ok(()));
+    }
 }
@@ -1,12 +1,40 @@
+mod adaptive;
+mod board;
+mod canvas;
+mod code;
+mod difficulty;
+mod drawing;
+mod generators;
 mod input;
 mod maze;
 mod movement;
+mod pause;
+mod race;
+mod recording;
 mod render;
+mod replay;
+mod settings;
+mod solver;
 mod state;
 mod timer;
+mod topology;
 
+use crate::games::{
+    clock::{self, Clock},
+    renderer::{RecordingRenderer, SharedRenderer},
+    storage::{self, SharedStorage},
+};
+use adaptive::{Adaptive, Controller};
+use board::{Board, Penalty};
+use difficulty::{Curve, Level};
+pub use generators::Algorithm;
+use maze::{Maze, MazeOptions, MAX_ASPECT, MAX_KEYS};
+use recording::{Race, Replay, Run};
 use serde::{Deserialize, Serialize};
-use std::{cell::RefCell, collections::HashSet, rc::Rc};
+pub use settings::{Direction, RenderMode, WallPenalty};
+use settings::{SaveExpiry, Settings};
+use std::{cell::RefCell, rc::Rc};
+pub use topology::Topology;
 use wasm_bindgen::prelude::*;
 use web_sys::{console, Document};
 
@@ -17,30 +45,91 @@ fn get_document() -> Document {
         .expect("no document exists")
 }
 
+// Until it is put on a page or handed a renderer, a game draws into memory
+fn detached_renderer() -> SharedRenderer {
+    Rc::new(RefCell::new(RecordingRenderer::default()))
+}
+
+// Until it is given storage, a game saves into memory
+fn detached_storage() -> SharedStorage {
+    Rc::new(RefCell::new(storage::MemoryStorage::default()))
+}
+
+// Seconds taken off the timer for each hint
+const HINT_PENALTY: i32 = 15;
+
+fn random_seed() -> u32 {
+    (js_sys::Math::random() * 4_294_967_296.0) as u32
+}
+
+/// Value of `name` in the page's query string, taken as is.
+fn query_param(name: &str) -> Option<String> {
+    let search = web_sys::window()?.location().search().ok()?;
+    search
+        .trim_start_matches('?')
+        .split('&')
+        .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+        .map(str::to_string)
+}
+
 #[wasm_bindgen]
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Perception {
-    // Game state
-    size: usize,
-    level: usize,
-    #[serde(default)]
-    moves: usize,  // <-- New field to record moves
-
-    // Maze elements
-    walls: Vec<bool>,
-    current_position: (usize, usize),
-    start_position: (usize, usize),
-    key_position: (usize, usize),
-    door_position: (usize, usize),
-    visited: HashSet<(usize, usize)>,
-    has_key: bool,
+    // Game state and maze elements
+    #[serde(flatten)]
+    board: Board,
 
     // Timer state
     time_remaining: i32,
     last_tick: f64,
+    // Wall-clock time the game was paused at, in milliseconds
+    #[serde(default)]
+    paused_at: Option<f64>,
+
+    // Moves attempted on the current maze, and on the last completed one
+    #[serde(default)]
+    run: Option<Run>,
+    #[serde(default)]
+    last_run: Option<Run>,
+    #[serde(skip)]
+    replay: Option<Replay>,
+    // Wall-clock time the open replay was opened at, in milliseconds
+    #[serde(skip)]
+    replay_opened_at: f64,
 
-    #[serde(skip, default = "get_document")]
-    document: Document,
+    // Fastest run on the last completed maze, and a retry of it under way
+    #[serde(default)]
+    best_run: Option<Run>,
+    #[serde(default)]
+    race: Option<Race>,
+    // Last race's time minus the best before it, in milliseconds
+    #[serde(default)]
+    race_margin: Option<f64>,
+
+    // What adaptive difficulty has learnt of the player
+    #[serde(default)]
+    adaptation: Controller,
+
+    // Preferences are saved separately, under `maze_settings`
+    #[serde(skip)]
+    settings: Settings,
+
+    // The page the game is on; None when it runs headless
+    #[serde(skip)]
+    document: Option<Document>,
+    #[serde(skip, default = "detached_renderer")]
+    renderer: SharedRenderer,
+    #[serde(skip, default = "detached_storage")]
+    storage: SharedStorage,
+    #[serde(skip, default = "clock::wall")]
+    clock: Clock,
+    // Set when playing a shared maze, whose session must not replace the
+    // saved game
+    #[serde(skip)]
+    unsaved: bool,
+    // The game `start` handed to the page's handlers
+    #[serde(skip)]
+    running: Option<Rc<RefCell<Perception>>>,
 }
 
 #[wasm_bindgen]
@@ -48,55 +137,504 @@ impl Perception {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Result<Self, JsValue> {
         let document = get_document();
-        let storage = web_sys::window()
-            .expect("no global window exists")
-            .local_storage()?
-            .expect("no local storage");
-
-        let now = js_sys::Date::now();
-        let mut game = if let Some(state) = storage.get_item("maze_state")? {
-            let last_save = storage
-                .get_item("maze_time")?
-                .unwrap_or_else(|| "0".to_string())
-                .parse::<f64>()
-                .unwrap_or(0.0);
-
-            // If more than 5 minutes (300000 ms) have passed since last save, create a new maze.
-            if now - last_save > 300000.0 {
-                Self::create_maze(2, document)
-            } else {
-                // Deserialize the saved state.
-                let mut game: Self =
-                    serde_wasm_bindgen::from_value(js_sys::JSON::parse(&state)?)?;
-                let now_secs = now / 1000.0;
-                let elapsed = now_secs - game.last_tick;
-                if elapsed as i32 >= game.time_remaining {
-                    // Timer expired: start a new maze.
-                    Self::create_maze(2, document)
-                } else {
-                    // Otherwise adjust the time remaining based on elapsed time.
-                    game.time_remaining -= elapsed as i32;
-                    game.last_tick = now_secs;
-                    game
+        let storage = storage::shared();
+        let clock = clock::wall();
+
+        // A shared maze in the URL takes precedence over saved progress,
+        // which is left for the next visit
+        if let Some(code) = query_param("maze") {
+            match code::decode(&code) {
+                Ok(maze) => {
+                    let mut game = Self::from_maze(maze, storage, clock)
+                        .unsaved()
+                        .on_page(document);
+                    game.render()?;
+                    game.start()?;
+                    return Ok(game);
                 }
+                Err(e) => console::log_1(&format!("Ignoring maze code: {e}").into()),
+            }
+        }
+
+        // Saved progress; unreadable saves start over at the level saved
+        let now = clock();
+        let game = match Self::restore(storage.clone(), clock.clone(), now) {
+            Ok(Some(game)) => game,
+            Ok(None) => Self::create(random_seed(), storage, clock),
+            Err(e) => {
+                console::log_1(&format!("Ignoring saved game: {e}").into());
+                let level = Self::saved_level(&storage);
+                let mut game = Self::create(random_seed(), storage, clock);
+                if let Some(level) = level {
+                    game.start_fresh(level, now);
+                }
+                game
             }
-        } else {
-            Self::create_maze(2, document)
         };
+        let mut game = game.on_page(document);
 
         game.render()?;
         game.start()?;
         Ok(game)
     }
+    /// Starts a fresh session at level 1 whose mazes are all derived from `seed`,
+    /// ignoring any saved progress.
+    #[wasm_bindgen]
+    pub fn with_seed(seed: u32) -> Result<Perception, JsValue> {
+        let mut game = Self::create(seed, storage::shared(), clock::wall()).on_page(get_document());
+        game.render()?;
+        game.start()?;
+        Ok(game)
+    }
+    /// Starts a fresh session on the maze a code from `maze_code` describes.
+    /// It is not saved, so saved progress is still there on the next visit.
+    #[wasm_bindgen]
+    pub fn from_code(code: &str) -> Result<Perception, JsValue> {
+        let maze = code::decode(code).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let mut game = Self::from_maze(maze, storage::shared(), clock::wall())
+            .unsaved()
+            .on_page(get_document());
+        game.render()?;
+        game.start()?;
+        Ok(game)
+    }
+    /// Compact URL-safe code for the current maze, for sharing as `?maze=`.
+    #[wasm_bindgen]
+    pub fn maze_code(&self) -> String {
+        self.live_ref(|game| code::encode(&game.board.maze))
+    }
+    #[wasm_bindgen(getter)]
+    pub fn seed(&self) -> u32 {
+        self.live_ref(|game| game.board.maze.seed)
+    }
+    /// Moves in the optimal route through the current maze.
+    #[wasm_bindgen(getter)]
+    pub fn par(&self) -> usize {
+        self.live_ref(|game| game.board.maze.par)
+    }
+    /// Optimal start → key → door route as cell indices (`y * width + x`).
+    #[wasm_bindgen]
+    pub fn solution(&self) -> Vec<usize> {
+        self.live_ref(|game| {
+            solver::solve(&game.board.maze)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|position| game.board.maze.cell_index(position))
+                .collect()
+        })
+    }
+    /// ASCII art of the current maze (square cells only), optionally with
+    /// the solution route and the start, keys and doors marked.
+    #[wasm_bindgen]
+    pub fn to_ascii(&self, solution: bool, items: bool) -> Option<String> {
+        self.live_ref(|game| drawing::ascii(&game.board.maze, drawing::Overlay { solution, items }))
+    }
+    /// A standalone SVG document of the current maze, for printing, with the
+    /// same optional overlays as `to_ascii`.
+    #[wasm_bindgen]
+    pub fn to_svg(&self, solution: bool, items: bool) -> String {
+        self.live_ref(|game| drawing::svg(&game.board.maze, drawing::Overlay { solution, items }))
+    }
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> usize {
+        self.live_ref(|game| game.board.maze.width)
+    }
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> usize {
+        self.live_ref(|game| game.board.maze.height)
+    }
+    #[wasm_bindgen(getter)]
+    pub fn topology(&self) -> Topology {
+        self.live_ref(|game| game.board.options.topology)
+    }
+    /// Switches between square, hex and triangle cells for the rest of the
+    /// session and regenerates the current level with them.
+    #[wasm_bindgen]
+    pub fn set_topology(&mut self, topology: Topology) -> Result<(), JsValue> {
+        self.live(|game| {
+            game.board.options.topology = topology;
+            game.regenerate_level()
+        })
+    }
+    #[wasm_bindgen(getter)]
+    pub fn aspect(&self) -> f64 {
+        self.live_ref(|game| game.board.options.aspect)
+    }
+    /// Sets the width to height ratio (0.25 to 4) for the rest of the session
+    /// and regenerates the current level with it; 1 keeps mazes square.
+    #[wasm_bindgen]
+    pub fn set_aspect(&mut self, aspect: f64) -> Result<(), JsValue> {
+        self.live(|game| {
+            game.board.options.aspect = aspect.clamp(1.0 / MAX_ASPECT, MAX_ASPECT);
+            game.regenerate_level()
+        })
+    }
+    #[wasm_bindgen(getter)]
+    pub fn keys(&self) -> usize {
+        self.live_ref(|game| game.board.options.keys)
+    }
+    /// Sets how many key/door pairs each maze has (1 to 5; fewer fit in small
+    /// mazes) and regenerates the current level with them.
+    #[wasm_bindgen]
+    pub fn set_keys(&mut self, keys: usize) -> Result<(), JsValue> {
+        self.live(|game| {
+            game.board.options.keys = keys.clamp(1, MAX_KEYS);
+            game.regenerate_level()
+        })
+    }
+    #[wasm_bindgen(getter)]
+    pub fn ordered_keys(&self) -> bool {
+        self.live_ref(|game| game.board.options.ordered_keys)
+    }
+    /// Requires keys to be collected in colour order, from the next maze on.
+    #[wasm_bindgen]
+    pub fn set_ordered_keys(&mut self, ordered: bool) {
+        self.live(|game| game.board.options.ordered_keys = ordered);
+    }
+    #[wasm_bindgen(getter)]
+    pub fn algorithm(&self) -> Algorithm {
+        self.live_ref(|game| game.board.options.algorithm)
+    }
+    /// Switches the generation algorithm for the rest of the session and
+    /// regenerates the current level with it.
+    #[wasm_bindgen]
+    pub fn set_algorithm(&mut self, algorithm: Algorithm) -> Result<(), JsValue> {
+        self.live(|game| {
+            game.board.options.algorithm = algorithm;
+            game.regenerate_level()
+        })
+    }
+    #[wasm_bindgen(getter)]
+    pub fn braid(&self) -> f64 {
+        self.live_ref(|game| game.board.options.braid)
+    }
+    /// Sets the chance (0 to 1) of each dead end being opened into a loop, for
+    /// the rest of the session, and regenerates the current level with it.
+    #[wasm_bindgen]
+    pub fn set_braid(&mut self, braid: f64) -> Result<(), JsValue> {
+        self.live(|game| {
+            game.board.options.braid = braid.clamp(0.0, 1.0);
+            game.regenerate_level()
+        })
+    }
+    #[wasm_bindgen(getter)]
+    pub fn wall_penalty(&self) -> WallPenalty {
+        match self.live_ref(|game| game.board.penalty) {
+            Penalty::Restart => WallPenalty::ResetToStart,
+            Penalty::Checkpoint => WallPenalty::ResetToCheckpoint,
+            Penalty::Time(_) => WallPenalty::LoseTime,
+            Penalty::Lives(_) => WallPenalty::LoseLife,
+        }
+    }
+    /// Seconds lost per wall hit, or lives a maze; 0 for the other penalties.
+    #[wasm_bindgen(getter)]
+    pub fn wall_penalty_amount(&self) -> u32 {
+        match self.live_ref(|game| game.board.penalty) {
+            Penalty::Time(seconds) => seconds as u32,
+            Penalty::Lives(lives) => lives as u32,
+            _ => 0,
+        }
+    }
+    /// Sets what walking into a wall costs and regenerates the current level
+    /// with it. `amount` is the seconds lost (1 to 300) for `LoseTime` or the
+    /// lives a maze (1 to 9) for `LoseLife`, and is ignored otherwise.
+    /// Remembered across visits.
+    #[wasm_bindgen]
+    pub fn set_wall_penalty(&mut self, mode: WallPenalty, amount: u32) -> Result<(), JsValue> {
+        let penalty = match mode {
+            WallPenalty::ResetToStart => Penalty::Restart,
+            WallPenalty::ResetToCheckpoint => Penalty::Checkpoint,
+            WallPenalty::LoseTime => Penalty::Time(amount.min(i32::MAX as u32) as i32),
+            WallPenalty::LoseLife => Penalty::Lives(amount as usize),
+        }
+        .clamped();
+        self.live(|game| {
+            game.settings.wall_penalty = penalty;
+            game.settings.save(&game.storage)?;
+            game.regenerate_level()
+        })
+    }
+    /// The difficulty curve, as JSON.
+    #[wasm_bindgen]
+    pub fn difficulty(&self) -> Result<String, JsValue> {
+        self.live_ref(|game| serde_json::to_string(&game.settings.difficulty))
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+    /// Replaces the difficulty curve with one read from JSON (see
+    /// `difficulty.rs`; `"{}"` restores the default) and starts the current
+    /// level over with it. What the curve sets overrides the player's own
+    /// algorithm, keys and wall penalty. Remembered across visits.
+    #[wasm_bindgen]
+    pub fn set_difficulty(&mut self, json: &str) -> Result<(), JsValue> {
+        let curve: Curve =
+            serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        curve.validate().map_err(|e| JsValue::from_str(&e))?;
+        self.live(|game| {
+            game.settings.difficulty = curve;
+            game.settings.save(&game.storage)?;
+            let level = game.replace_maze();
+            game.time_remaining = game.time_limit_for(&level);
+            game.last_tick = game.now() / 1000.0;
+            game.render()
+        })
+    }
+    /// Seconds saved progress keeps its maze and clock.
+    #[wasm_bindgen(getter)]
+    pub fn save_expiry(&self) -> u32 {
+        self.live_ref(|game| game.settings.save_expiry.after)
+    }
+    /// Levels an expired save drops on the fresh maze it starts.
+    #[wasm_bindgen(getter)]
+    pub fn save_expiry_decay(&self) -> usize {
+        self.live_ref(|game| game.settings.save_expiry.decay)
+    }
+    /// Sets how long saved progress keeps its maze and clock (1 minute to
+    /// 1 week, in seconds). After that a visit starts a fresh maze at the
+    /// saved level less `decay` levels, but not below level 1. Remembered
+    /// across visits.
+    #[wasm_bindgen]
+    pub fn set_save_expiry(&mut self, seconds: u32, decay: usize) -> Result<(), JsValue> {
+        self.live(|game| {
+            game.settings.save_expiry = SaveExpiry {
+                after: seconds,
+                decay,
+            }
+            .clamped();
+            game.settings.save(&game.storage)
+        })
+    }
+    #[wasm_bindgen(getter)]
+    pub fn adaptive(&self) -> bool {
+        self.live_ref(|game| game.settings.adaptive.enabled)
+    }
+    #[wasm_bindgen(getter)]
+    pub fn adaptive_target(&self) -> f64 {
+        self.live_ref(|game| game.settings.adaptive.target)
+    }
+    /// Turns adaptive difficulty on or off. When on, the next maze grows or
+    /// shrinks, and its time limit changes, with how the player has been
+    /// doing, so that they complete about `target` (0.5 to 0.95) of levels.
+    /// Remembered across visits.
+    #[wasm_bindgen]
+    pub fn set_adaptive(&mut self, enabled: bool, target: f64) -> Result<(), JsValue> {
+        let adaptive = Adaptive {
+            enabled,
+            target: target.clamp(0.5, 0.95),
+        };
+        self.live(|game| {
+            game.settings.adaptive = adaptive;
+            game.settings.save(&game.storage)
+        })
+    }
+    /// Share of the last ten levels played that were completed, while levels
+    /// adapt.
+    #[wasm_bindgen(getter)]
+    pub fn recent_success_rate(&self) -> Option<f64> {
+        self.live_ref(|game| game.adaptation.success_rate())
+    }
+    #[wasm_bindgen(getter)]
+    pub fn render_mode(&self) -> RenderMode {
+        self.live_ref(|game| game.settings.render_mode)
+    }
+    /// Switches between drawing cells as elements or on a canvas, which
+    /// keeps large mazes responsive. Remembered across visits.
+    #[wasm_bindgen]
+    pub fn set_render_mode(&mut self, mode: RenderMode) -> Result<(), JsValue> {
+        self.live(|game| {
+            game.settings.render_mode = mode;
+            if let Some(document) = &game.document {
+                game.renderer = render::renderer_for(mode, document);
+            }
+            game.settings.save(&game.storage)?;
+            game.render()
+        })
+    }
+    /// Binds a key, by its `KeyboardEvent.code` (e.g. `"KeyW"`), to a move.
+    #[wasm_bindgen]
+    pub fn bind_key(&mut self, code: String, direction: Direction) -> Result<(), JsValue> {
+        self.live(|game| {
+            game.settings.key_bindings.insert(code, direction);
+            game.settings.save(&game.storage)
+        })
+    }
+    #[wasm_bindgen]
+    pub fn unbind_key(&mut self, code: &str) -> Result<(), JsValue> {
+        self.live(|game| {
+            game.settings.key_bindings.remove(code);
+            game.settings.save(&game.storage)
+        })
+    }
+    /// Restores arrow keys, WASD and Q/E/Z/C for diagonals.
+    #[wasm_bindgen]
+    pub fn reset_key_bindings(&mut self) -> Result<(), JsValue> {
+        self.live(|game| {
+            game.settings.key_bindings = Settings::default().key_bindings;
+            game.settings.save(&game.storage)
+        })
+    }
+    #[wasm_bindgen(getter)]
+    pub fn swipe_distance(&self) -> f64 {
+        self.live_ref(|game| game.settings.swipe_distance)
+    }
+    #[wasm_bindgen(getter)]
+    pub fn swipe_duration(&self) -> f64 {
+        self.live_ref(|game| game.settings.swipe_duration)
+    }
+    /// Sets the shortest drag, in CSS pixels (5 to 200), and the longest
+    /// one, in milliseconds (100 to 2000), that count as a swipe.
+    /// Remembered across visits.
+    #[wasm_bindgen]
+    pub fn set_swipe_thresholds(&mut self, distance: f64, duration: f64) -> Result<(), JsValue> {
+        self.live(|game| {
+            game.settings.swipe_distance = distance.clamp(5.0, 200.0);
+            game.settings.swipe_duration = duration.clamp(100.0, 2000.0);
+            game.settings.save(&game.storage)
+        })
+    }
+    #[wasm_bindgen(getter)]
+    pub fn gamepad_dead_zone(&self) -> f64 {
+        self.live_ref(|game| game.settings.gamepad_dead_zone)
+    }
+    /// Sets how far (0 to 0.9 of full deflection) a gamepad stick must move
+    /// before it steers. Remembered across visits.
+    #[wasm_bindgen]
+    pub fn set_gamepad_dead_zone(&mut self, dead_zone: f64) -> Result<(), JsValue> {
+        self.live(|game| {
+            game.settings.gamepad_dead_zone = dead_zone.clamp(0.0, 0.9);
+            game.settings.save(&game.storage)
+        })
+    }
+    /// Moves attempted so far on the current maze, as JSON: each with its
+    /// time in milliseconds since the maze appeared, cells and outcome.
+    #[wasm_bindgen]
+    pub fn move_log(&self) -> Result<String, JsValue> {
+        self.live_ref(|game| {
+            serde_json::to_string(&game.run).map_err(|e| JsValue::from_str(&e.to_string()))
+        })
+    }
+    /// The full run of the last completed level, including its maze, as JSON.
+    #[wasm_bindgen]
+    pub fn last_run_log(&self) -> Result<String, JsValue> {
+        self.live_ref(|game| {
+            serde_json::to_string(&game.last_run).map_err(|e| JsValue::from_str(&e.to_string()))
+        })
+    }
+    /// Replays the last completed level in place of the game, which is held
+    /// (timer included) until `stop_replay`.
+    #[wasm_bindgen]
+    pub fn replay(&mut self) -> Result<(), JsValue> {
+        self.live(|game| {
+            if game.race.is_some() {
+                return Ok(());
+            }
+            if let Some(run) = game.last_run.clone() {
+                game.open_replay(run);
+                game.render()?;
+            }
+            Ok(())
+        })
+    }
+    /// Pauses or resumes the replay; a finished replay starts over.
+    #[wasm_bindgen]
+    pub fn toggle_replay(&mut self) -> Result<(), JsValue> {
+        self.live(|game| {
+            if let Some(replay) = game.replay.as_mut() {
+                if replay.finished() {
+                    replay.rewind();
+                } else {
+                    replay.playing = !replay.playing;
+                }
+                game.render()?;
+            }
+            Ok(())
+        })
+    }
+    /// Sets the replay speed as a multiple of real time (0.25 to 8).
+    #[wasm_bindgen]
+    pub fn set_replay_speed(&mut self, speed: f64) -> Result<(), JsValue> {
+        self.live(|game| {
+            if let Some(replay) = game.replay.as_mut() {
+                replay.speed = speed.clamp(0.25, 8.0);
+                game.render()?;
+            }
+            Ok(())
+        })
+    }
+    /// Closes the replay and returns to the game.
+    #[wasm_bindgen]
+    pub fn stop_replay(&mut self) -> Result<(), JsValue> {
+        self.live(|game| {
+            game.close_replay();
+            game.render()
+        })
+    }
+    /// Retries the last completed maze against a ghost that follows the best
+    /// run on it. The interrupted game resumes when the race ends.
+    #[wasm_bindgen]
+    pub fn race_best(&mut self) -> Result<(), JsValue> {
+        self.live(|game| {
+            if game.race.is_none() {
+                if let Some(best) = game.best_run.clone() {
+                    game.start_race(best);
+                    game.render()?;
+                }
+            }
+            Ok(())
+        })
+    }
+    /// Abandons the race and returns to the interrupted game.
+    #[wasm_bindgen]
+    pub fn end_race(&mut self) -> Result<(), JsValue> {
+        self.live(|game| {
+            game.resume_game();
+            game.render()
+        })
+    }
+    /// Stops the clock and hides the maze until `resume`. Also happens by
+    /// itself when the tab is hidden.
+    #[wasm_bindgen]
+    pub fn pause(&mut self) -> Result<(), JsValue> {
+        self.live(|game| game.set_paused(true))
+    }
+    #[wasm_bindgen]
+    pub fn resume(&mut self) -> Result<(), JsValue> {
+        self.live(|game| game.set_paused(false))
+    }
+    #[wasm_bindgen(getter)]
+    pub fn paused(&self) -> bool {
+        self.live_ref(|game| game.paused_at.is_some())
+    }
     #[wasm_bindgen]
     pub fn start(&mut self) -> Result<(), JsValue> {
-        let game_state = Rc::new(RefCell::new(self.clone()));
+        let game_state = Rc::new(RefCell::new(Perception {
+            running: None,
+            ..self.clone()
+        }));
+        self.running = Some(game_state.clone());
+        let document = self.page()?;
 
-        Self::setup_click_handler(game_state.clone())?;
+        for source in input::sources() {
+            source.attach(game_state.clone())?;
+        }
         Self::setup_timer(game_state.clone())?;
+        Self::setup_replay(game_state.clone())?;
+        Self::setup_pause(game_state.clone())?;
+
+        // Set up hint button handler
+        if let Some(hint_btn) = document.get_element_by_id("hint") {
+            let game_state = game_state.clone();
+            let handler = Closure::wrap(Box::new(move |_event: web_sys::MouseEvent| {
+                if let Ok(mut game) = game_state.try_borrow_mut() {
+                    game.hint().unwrap();
+                }
+            }) as Box<dyn FnMut(_)>);
+
+            hint_btn.add_event_listener_with_callback("click", handler.as_ref().unchecked_ref())?;
+            handler.forget();
+        }
 
         // Set up reset button handler
-        if let Some(reset_btn) = self.document.get_element_by_id("reset-level") {
+        if let Some(reset_btn) = document.get_element_by_id("reset-level") {
             let game_state = game_state.clone();
             let handler = Closure::wrap(Box::new(move |_event: web_sys::MouseEvent| {
                 if let Ok(mut game) = game_state.try_borrow_mut() {
@@ -112,93 +650,414 @@ impl Perception {
         console::log_1(&"Setup complete".into());
         Ok(())
     }
+    /// Highlights the next cell towards the key (or the door once it is held),
+    /// at the cost of `HINT_PENALTY` seconds.
+    #[wasm_bindgen]
+    pub fn hint(&mut self) -> Result<(), JsValue> {
+        self.live(|game| {
+            if game.replay.is_some() || game.paused_at.is_some() {
+                return Ok(());
+            }
+            if let Some(step) = game.board.hint() {
+                game.time_remaining = (game.time_remaining - HINT_PENALTY).max(0);
+                game.render()?;
+                game.highlight_hint(step)?;
+            }
+            Ok(())
+        })
+    }
     #[wasm_bindgen]
     pub fn reset(&mut self) {
-        let new_game = Self::create_maze(self.size, self.document.clone());
-        self.walls = new_game.walls;
-        self.key_position = new_game.key_position;
-        self.door_position = new_game.door_position;
-        self.reset_position();
-
-        // Reset timer state completely
-        self.moves = 0;
-        self.time_remaining = 300;
-        self.last_tick = js_sys::Date::now() / 1000.0;
-
-        // Force timer display update
-        if let Some(timer_el) = self.document.get_element_by_id("timer") {
-            timer_el.set_text_content(Some("5:00"));
-        }
+        self.live(|game| {
+            game.resume_game();
+            let level = game.level_setup(game.board.level);
+            game.board.regenerate(&level);
+
+            // Reset timer state completely
+            game.board.moves = 0;
+            game.time_remaining = game.time_limit_for(&level);
+            game.last_tick = game.now() / 1000.0;
+            game.begin_run();
 
-        // Update display
-        self.render().expect("Failed to render reset");
-    }
-    fn reset_position(&mut self) {
-        let old_pos = self.current_position;
-        self.current_position = self.start_position;
-        self.visited.clear();
-        self.visited.insert(self.start_position);
-        self.has_key = false;
-
-        // Update only the changed cells rather than the entire grid.
-        let maze = self
-            .document
-            .get_element_by_id("maze")
-            .expect("Maze element not found");
-
-        // Helper closure to update a specific cell.
-        let update_cell = |x: usize, y: usize| -> Result<(), JsValue> {
-            let index = (y * self.size + x) as u32;
-            if let Some(cell) = maze.children().item(index) {
-                self.update_cell_state(&cell, x, y)?;
+            // Update display
+            game.render().expect("Failed to render reset");
+        })
+    }
+    #[wasm_bindgen]
+    pub fn reset_to_level_one(&mut self) -> Result<(), JsValue> {
+        self.live(|game| {
+            game.resume_game();
+            // Only reset if above level 1
+            if game.board.level > 1 {
+                let level = game.level_setup(1);
+                game.board.restart_at_level(1, &level);
+                game.time_remaining = game.time_limit_for(&level);
+                game.last_tick = game.now() / 1000.0;
+                game.begin_run();
+
+                // Show/hide reset button based on level
+                game.renderer
+                    .borrow_mut()
+                    .control_shown("reset-level", false)?;
+
+                // Save state
+                game.save_state()?;
+                game.render()?;
             }
             Ok(())
+        })
+    }
+}
+
+impl Perception {
+    /// A fresh session from `seed`, off the page: it draws through
+    /// `renderer`, e.g. a `RecordingRenderer` to assert on in tests, keeps
+    /// its settings and saves in `storage` and reads the time from `clock`.
+    /// No input handlers or timers are attached.
+    pub fn headless(
+        seed: u32,
+        renderer: SharedRenderer,
+        storage: SharedStorage,
+        clock: Clock,
+    ) -> Result<Perception, JsValue> {
+        let mut game = Self::create(seed, storage, clock);
+        game.renderer = renderer;
+        game.render()?;
+        Ok(game)
+    }
+
+    fn create(seed: u32, storage: SharedStorage, clock: Clock) -> Self {
+        let settings = Settings::load(&storage);
+        let level = settings
+            .difficulty
+            .level(1, MazeOptions::default(), settings.wall_penalty);
+        let (width, height) = level.options.dimensions(level.size);
+        let maze = Maze::generate(width, height, seed, level.options);
+        Self::from_maze(maze, storage, clock)
+    }
+
+    /// A fresh session at level 1 on `maze`, later mazes taking its options.
+    /// It is off the page until `on_page`.
+    fn from_maze(maze: Maze, storage: SharedStorage, clock: Clock) -> Self {
+        let settings = Settings::load(&storage);
+        let level = settings
+            .difficulty
+            .level(1, maze.options, settings.wall_penalty);
+        let board = Board {
+            penalty: level.penalty,
+            ..Board::new(maze, 1)
+        };
+        let now = clock();
+        Self {
+            renderer: detached_renderer(),
+            run: Some(Run::new(&board, now)),
+            board,
+            time_remaining: level.time_limit,
+            last_tick: now / 1000.0,
+            paused_at: None,
+            last_run: None,
+            replay: None,
+            replay_opened_at: 0.0,
+            best_run: None,
+            race: None,
+            race_margin: None,
+            adaptation: Controller::default(),
+            settings,
+            document: None,
+            storage,
+            clock,
+            unsaved: false,
+            running: None,
+        }
+    }
+
+    /// Keeps the session from saving, as for a shared maze.
+    fn unsaved(mut self) -> Self {
+        self.unsaved = true;
+        self
+    }
+
+    /// Puts the game on `document`, drawn the way its settings say.
+    fn on_page(mut self, document: Document) -> Self {
+        self.renderer = render::renderer_for(self.settings.render_mode, &document);
+        self.document = Some(document);
+        self
+    }
+
+    /// The page the game is on, for attaching handlers.
+    fn page(&self) -> Result<Document, JsValue> {
+        self.document
+            .clone()
+            .ok_or_else(|| JsValue::from_str("the game is not on a page"))
+    }
+
+    /// Milliseconds on the game's clock.
+    fn now(&self) -> f64 {
+        (self.clock)()
+    }
+
+    /// How level `number` is set up: by the difficulty curve, and where it
+    /// leaves a choice, by the player.
+    fn level_setup(&self, number: usize) -> Level {
+        self.settings
+            .difficulty
+            .level(number, self.board.options, self.settings.wall_penalty)
+    }
+
+    /// Replaces the current level's maze after an option change.
+    fn regenerate_level(&mut self) -> Result<(), JsValue> {
+        self.replace_maze();
+        self.render()
+    }
+
+    /// Puts a fresh maze for the current level on the board, without
+    /// redrawing, and returns how the level is now set up.
+    fn replace_maze(&mut self) -> Level {
+        let level = self.level_setup(self.board.level);
+        self.board.regenerate(&level);
+        self.board.moves = 0;
+        self.begin_run();
+        level
+    }
+
+    /// Runs `f` on the game the page's handlers share once `start` has been
+    /// called, so exports reach the game being played; else on this one.
+    fn live<R>(&mut self, f: impl FnOnce(&mut Perception) -> R) -> R {
+        match self.running.clone() {
+            Some(game) => f(&mut game.borrow_mut()),
+            None => f(self),
+        }
+    }
+
+    /// Reads from the game the page's handlers share, as `live` does.
+    fn live_ref<R>(&self, f: impl FnOnce(&Perception) -> R) -> R {
+        match &self.running {
+            Some(game) => f(&game.borrow()),
+            None => f(self),
+        }
+    }
+
+    /// Starts recording moves afresh on the board's current maze.
+    fn begin_run(&mut self) {
+        self.run = Some(Run::new(&self.board, self.now()));
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use crate::games::storage::{MemoryStorage, Storage};
+    use board::MoveResult;
+    use input::MoveIntent;
+    use std::cell::Cell;
+
+    /// A headless game with the renderer, storage and clock it was given.
+    pub(in crate::games::perception) struct Harness {
+        pub(in crate::games::perception) game: Perception,
+        pub(in crate::games::perception) renderer: Rc<RefCell<RecordingRenderer>>,
+        pub(in crate::games::perception) storage: Rc<RefCell<MemoryStorage>>,
+        pub(in crate::games::perception) time: Rc<Cell<f64>>,
+    }
+
+    pub(in crate::games::perception) fn headless(seed: u32) -> Harness {
+        let renderer = Rc::new(RefCell::new(RecordingRenderer::default()));
+        let storage = Rc::new(RefCell::new(MemoryStorage::default()));
+        let time = Rc::new(Cell::new(1_000_000.0));
+        let clock = {
+            let time = time.clone();
+            Rc::new(move || time.get())
         };
+        let game = Perception::headless(seed, renderer.clone(), storage.clone(), clock).unwrap();
+        Harness {
+            game,
+            renderer,
+            storage,
+            time,
+        }
+    }
+
+    #[test]
+    fn a_headless_game_draws_its_first_maze() {
+        let Harness { game, renderer, .. } = headless(1);
+        let renderer = renderer.borrow();
+        let maze = &game.board.maze;
 
-        // Update the old cell if it differs from start_position.
-        if old_pos != self.start_position {
-            let _ = update_cell(old_pos.0, old_pos.1);
+        let layout = renderer.layout.as_ref().unwrap();
+        assert_eq!(layout.columns, Some(maze.width));
+        assert_eq!(layout.cells.len(), maze.width * maze.height);
+        assert_eq!(renderer.cells.len(), maze.width * maze.height);
+
+        let start = &renderer.cells[maze.cell_index(maze.start_position)];
+        assert_eq!(start.text, "👤");
+        assert!(start.classes.contains(&"current".to_string()));
+        assert_eq!(renderer.cells[maze.cell_index(maze.keys[0])].text, "🔑");
+        assert_eq!(renderer.cells[maze.cell_index(maze.doors[0])].text, "🚪");
+
+        assert_eq!(renderer.stats["level"], "1");
+        assert_eq!(renderer.stats["keys"], "0/1");
+        assert_eq!(renderer.stats["par"], maze.par.to_string());
+        assert_eq!(renderer.timer, game.timer_text());
+    }
+
+    #[test]
+    fn walking_the_solution_completes_the_level() {
+        let Harness {
+            mut game, renderer, ..
+        } = headless(2);
+        let route = solver::solve(&game.board.maze).unwrap();
+        let mut results = Vec::new();
+        for &(x, y) in &route[1..] {
+            results.push(game.apply_input(MoveIntent::Cell(x, y)).unwrap());
         }
-        let _ = update_cell(self.start_position.0, self.start_position.1);
+
+        assert!(results.contains(&MoveResult::KeyCollected));
+        assert_eq!(results.last(), Some(&MoveResult::LevelComplete));
+        let renderer = renderer.borrow();
+        assert_eq!(renderer.stats["level"], "2");
+        assert_eq!(renderer.stats["moves"], "0");
+        assert_eq!(renderer.stats["efficiency"], "100%");
     }
-    #[wasm_bindgen]
-    pub fn reset_to_level_one(&mut self) -> Result<(), JsValue> {
-        // Only reset if above level 1
-        if self.size > 2 {
-            self.size = 2; // Level 1 starts with size 2
-            self.level = 1;
-
-            // Create new level 1 maze
-            let new_game = Self::create_maze(self.size, self.document.clone());
-            self.walls = new_game.walls;
-            self.key_position = new_game.key_position;
-            self.door_position = new_game.door_position;
-
-            // Reset position and timer
-            self.reset_position();
-            self.time_remaining = 300;
-            self.last_tick = js_sys::Date::now() / 1000.0;
-
-            // Update displays
-            if let Some(level_el) = self.document.get_element_by_id("level") {
-                level_el.set_text_content(Some("1"));
-            }
-            if let Some(el) = self.document.get_element_by_id("moves") {
-                el.set_text_content(Some("0"));
-            }
-            if let Some(timer_el) = self.document.get_element_by_id("timer") {
-                timer_el.set_text_content(Some("5:00"));
-            }
 
-            // Show/hide reset button based on level
-            if let Some(reset_btn) = self.document.get_element_by_id("reset-level") {
-                reset_btn.set_attribute("hidden", "")?;
-            }
+    #[test]
+    fn walking_into_a_wall_is_drawn_as_a_wall_hit() {
+        let Harness {
+            mut game, renderer, ..
+        } = headless(3);
+        // Level 1's maze is too small to have a wall that is not the door's
+        game.set_aspect(MAX_ASPECT).unwrap();
+        let maze = &game.board.maze;
+        let from = maze.start_position;
+        let grid = maze.grid();
+        let (blocked, wall, _) = grid
+            .neighbours(grid.cell(from))
+            .into_iter()
+            .find(|&(neighbour, _, _)| {
+                let position = grid.position(neighbour);
+                maze.has_wall(from, position) && maze.door_at(position).is_none()
+            })
+            .unwrap();
+        let (x, y) = grid.position(blocked);
+
+        let result = game.apply_input(MoveIntent::Cell(x, y)).unwrap();
+
+        assert_eq!(result, MoveResult::WallHit);
+        let index = game.board.maze.cell_index(from);
+        assert_eq!(renderer.borrow().wall_hits, vec![(index, wall)]);
+    }
+
+    #[test]
+    fn rebinding_a_key_changes_which_key_moves() {
+        let Harness { mut game, .. } = headless(6);
+        game.set_aspect(MAX_ASPECT).unwrap();
+        let maze = &game.board.maze;
+        let from = maze.start_position;
+        let (direction, to) = [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ]
+        .iter()
+        .find_map(|&direction| {
+            let to = maze.neighbour_towards(from, direction.vector())?;
+            let open = !maze.has_wall(from, to) && maze.door_at(to).is_none();
+            Some((direction, to)).filter(|_| open)
+        })
+        .unwrap();
+        let default_key = game
+            .settings
+            .key_bindings
+            .iter()
+            .find(|&(_, &bound)| bound == direction)
+            .map(|(code, _)| code.clone())
+            .unwrap();
+
+        game.unbind_key(&default_key).unwrap();
+        assert_eq!(game.press_key(&default_key).unwrap(), None);
+        assert_eq!(game.board.current_position, from);
+
+        game.bind_key("KeyX".to_string(), direction).unwrap();
+        assert!(game.press_key("KeyX").unwrap().is_some());
+        assert_eq!(game.board.current_position, to);
+    }
 
-            // Save state
-            self.save_state()?;
-            self.render()?;
+    #[test]
+    fn pausing_stops_the_run_clock_and_shows_the_overlay() {
+        let Harness {
+            mut game,
+            renderer,
+            time,
+            ..
+        } = headless(4);
+        let started_at = game.run.as_ref().unwrap().started_at;
+
+        game.pause().unwrap();
+        assert!(!renderer.borrow().hidden["pause-overlay"]);
+        assert!(renderer.borrow().hidden["pause"]);
+
+        time.set(time.get() + 5000.0);
+        game.resume().unwrap();
+        assert!(renderer.borrow().hidden["pause-overlay"]);
+        assert_eq!(game.run.as_ref().unwrap().started_at, started_at + 5000.0);
+        assert_eq!(game.last_tick, time.get() / 1000.0);
+    }
+
+    #[test]
+    fn time_spent_on_a_replay_is_left_out_of_the_run() {
+        let Harness { mut game, time, .. } = headless(7);
+        let route = solver::solve(&game.board.maze).unwrap();
+        for &(x, y) in &route[1..] {
+            game.apply_input(MoveIntent::Cell(x, y)).unwrap();
         }
-        Ok(())
+        let started_at = game.run.as_ref().unwrap().started_at;
+
+        game.replay().unwrap();
+        time.set(time.get() + 3000.0);
+        game.pause().unwrap();
+        time.set(time.get() + 2000.0);
+        game.resume().unwrap();
+        time.set(time.get() + 1000.0);
+        game.stop_replay().unwrap();
+
+        assert!(game.replay.is_none());
+        assert_eq!(game.run.as_ref().unwrap().started_at, started_at + 6000.0);
+    }
+
+    #[test]
+    fn shared_mazes_leave_the_saved_game_alone() {
+        let Harness {
+            mut game, storage, ..
+        } = headless(9);
+        game.pause().unwrap();
+        let saved = storage.borrow().get_item("maze_state");
+
+        let maze = Maze::generate(5, 5, 10, MazeOptions::default());
+        let mut shared = Perception::from_maze(maze, storage.clone(), game.clock.clone()).unsaved();
+        shared.pause().unwrap();
+        shared.resume().unwrap();
+        assert_eq!(storage.borrow().get_item("maze_state"), saved);
+    }
+
+    #[test]
+    fn saves_go_to_the_storage_given() {
+        let Harness {
+            mut game,
+            storage,
+            time,
+            ..
+        } = headless(5);
+        game.pause().unwrap();
+
+        let saved = storage.borrow();
+        assert_eq!(saved.get_item("maze_level").as_deref(), Some("1"));
+        assert_eq!(saved.get_item("maze_time"), Some(time.get().to_string()));
+        drop(saved);
+
+        let clock = game.clock.clone();
+        let restored = Perception::restore(storage, clock, time.get())
+            .unwrap()
+            .unwrap();
+        assert_eq!(restored.board.maze, game.board.maze);
+        assert_eq!(restored.paused_at, Some(time.get()));
     }
 }
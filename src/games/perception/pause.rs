@@ -0,0 +1,74 @@
+use super::Perception;
+use crate::games::renderer::Renderer;
+use std::{cell::RefCell, rc::Rc};
+use wasm_bindgen::prelude::*;
+
+impl Perception {
+    /// Pauses the game whenever its tab is hidden, which browsers also do
+    /// before a reload or close, and hooks up the pause and resume buttons.
+    pub(super) fn setup_pause(game_state: Rc<RefCell<Perception>>) -> Result<(), JsValue> {
+        let document = game_state.borrow().page()?;
+
+        let on_visibility = {
+            let game_state = game_state.clone();
+            let document = document.clone();
+            Closure::wrap(Box::new(move || {
+                if !document.hidden() {
+                    return;
+                }
+                if let Ok(mut game) = game_state.try_borrow_mut() {
+                    game.set_paused(true).unwrap();
+                }
+            }) as Box<dyn FnMut()>)
+        };
+        document.add_event_listener_with_callback(
+            "visibilitychange",
+            on_visibility.as_ref().unchecked_ref(),
+        )?;
+        on_visibility.forget();
+
+        for (id, paused) in [("pause", true), ("resume", false)] {
+            if let Some(button) = document.get_element_by_id(id) {
+                let game_state = game_state.clone();
+                let handler = Closure::wrap(Box::new(move |_event: web_sys::MouseEvent| {
+                    if let Ok(mut game) = game_state.try_borrow_mut() {
+                        game.set_paused(paused).unwrap();
+                    }
+                }) as Box<dyn FnMut(_)>);
+
+                button
+                    .add_event_listener_with_callback("click", handler.as_ref().unchecked_ref())?;
+                handler.forget();
+            }
+        }
+        Ok(())
+    }
+
+    /// Stops or restarts the clock, the ghost and any replay. Time spent
+    /// paused counts neither against the timer nor towards the run.
+    pub(super) fn set_paused(&mut self, paused: bool) -> Result<(), JsValue> {
+        let now = self.now();
+        match (self.paused_at, paused) {
+            (None, true) => self.paused_at = Some(now),
+            (Some(paused_at), false) => {
+                self.paused_at = None;
+                if let Some(run) = self.run.as_mut() {
+                    run.started_at += now - paused_at;
+                }
+                // Else the pause would be taken off twice when the replay closes
+                self.replay_opened_at += now - paused_at;
+                self.last_tick = now / 1000.0;
+            }
+            _ => return Ok(()),
+        }
+        self.save_state()?;
+        self.render()
+    }
+
+    /// Shows the overlay over the maze while paused, and the matching button.
+    pub(super) fn update_pause_controls(&self, renderer: &mut dyn Renderer) -> Result<(), JsValue> {
+        let paused = self.paused_at.is_some();
+        renderer.control_shown("pause-overlay", paused)?;
+        renderer.control_shown("pause", !paused)
+    }
+}
@@ -0,0 +1,176 @@
+use super::{
+    board::Board,
+    recording::{Race, Replay, Run},
+    Perception,
+};
+use crate::games::renderer::Renderer;
+use wasm_bindgen::prelude::*;
+
+impl Perception {
+    /// Sets the game aside and puts the best run's maze back on the board,
+    /// with that run's ghost starting alongside.
+    pub(super) fn start_race(&mut self, best: Run) {
+        let now = self.now();
+        let time_limit = self.level_setup(best.level).time_limit;
+        let race_board = Board {
+            penalty: self.board.penalty,
+            ..Board::new(best.maze.clone(), best.level)
+        };
+        let board = std::mem::replace(&mut self.board, race_board);
+        self.race = Some(Race {
+            ghost: Replay::new(best),
+            board,
+            time_remaining: self.time_remaining,
+            run: self.run.take(),
+            started_at: now,
+        });
+        self.close_replay();
+        self.race_margin = None;
+        self.time_remaining = time_limit;
+        self.last_tick = now / 1000.0;
+        self.begin_run();
+    }
+
+    /// Ends the race with the exit reached on `run`, keeping it as the new
+    /// best if it beat the ghost, and brings the game back.
+    pub(super) fn finish_race(&mut self, run: Option<Run>) {
+        let best = self.best_run.as_ref();
+        let times = run.as_ref().zip(best).and_then(|(run, best)| {
+            (run.maze == best.maze).then_some((run.duration()?, best.duration()?))
+        });
+        if let Some((time, best_time)) = times {
+            self.race_margin = Some(time - best_time);
+            if time < best_time {
+                self.best_run = run;
+            }
+        }
+        self.resume_game();
+    }
+
+    /// Drops the race, if any, and restores the game it interrupted. The
+    /// game's run is moved on by the race's length, so its times leave it out.
+    pub(super) fn resume_game(&mut self) {
+        if let Some(race) = self.race.take() {
+            let now = self.now();
+            let raced = now - race.started_at;
+            self.board = race.board;
+            self.time_remaining = race.time_remaining;
+            self.last_tick = now / 1000.0;
+            self.run = race.run.map(|run| Run {
+                started_at: run.started_at + raced,
+                ..run
+            });
+        }
+    }
+
+    /// Moves the ghost on by `wall_time` milliseconds, redrawing if it moved.
+    pub(super) fn step_ghost(&mut self, wall_time: f64) -> Result<(), JsValue> {
+        let Some(race) = self.race.as_mut() else {
+            return Ok(());
+        };
+        if race.ghost.advance(wall_time).is_empty() {
+            return Ok(());
+        }
+        self.render()
+    }
+
+    /// Where the ghost is, while a race is on screen.
+    pub(super) fn ghost_position(&self) -> Option<(usize, usize)> {
+        match (&self.race, &self.replay) {
+            (Some(race), None) => Some(race.ghost.board.current_position),
+            _ => None,
+        }
+    }
+
+    /// Shows the race buttons and the best time, with the last race's margin.
+    pub(super) fn update_race_controls(&self, renderer: &mut dyn Renderer) -> Result<(), JsValue> {
+        let idle = self.race.is_none() && self.replay.is_none();
+        renderer.control_shown("race", idle && self.best_run.is_some())?;
+        renderer.control_shown("race-stop", self.race.is_some())?;
+
+        let best = match (
+            self.best_run.as_ref().and_then(Run::duration),
+            self.race_margin,
+        ) {
+            (Some(best), Some(margin)) => {
+                format!("{:.1}s ({:+.1}s)", best / 1000.0, margin / 1000.0)
+            }
+            (Some(best), None) => format!("{:.1}s", best / 1000.0),
+            _ => "–".to_string(),
+        };
+        renderer.stats_changed(&[("best", best)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::perception::{
+        board::MoveResult,
+        maze::{Maze, MazeOptions},
+        tests::{headless, Harness},
+    };
+
+    /// A run on `maze` that reached the exit `duration` ms in.
+    fn finished_run(maze: &Maze, duration: f64) -> Run {
+        let mut run = Run::new(&Board::new(maze.clone(), 1), 0.0);
+        let exit = maze.start_position;
+        run.record(duration, exit, exit, MoveResult::LevelComplete);
+        run
+    }
+
+    /// A game racing a best run of `best` ms on its own maze.
+    fn racing(best: f64) -> Harness {
+        let mut harness = headless(8);
+        let game = &mut harness.game;
+        let best = finished_run(&game.board.maze, best);
+        game.best_run = Some(best.clone());
+        game.start_race(best);
+        harness
+    }
+
+    fn best_time(game: &Perception) -> Option<f64> {
+        game.best_run.as_ref().and_then(Run::duration)
+    }
+
+    #[test]
+    fn a_faster_run_becomes_the_best() {
+        let Harness { mut game, .. } = racing(5000.0);
+        let run = finished_run(&game.board.maze, 4000.0);
+
+        game.finish_race(Some(run));
+        assert!(game.race.is_none());
+        assert_eq!(best_time(&game), Some(4000.0));
+        assert_eq!(game.race_margin, Some(-1000.0));
+    }
+
+    #[test]
+    fn a_slower_run_keeps_the_best() {
+        let Harness { mut game, .. } = racing(5000.0);
+        let run = finished_run(&game.board.maze, 6500.0);
+
+        game.finish_race(Some(run));
+        assert_eq!(best_time(&game), Some(5000.0));
+        assert_eq!(game.race_margin, Some(1500.0));
+    }
+
+    #[test]
+    fn a_run_on_another_maze_is_not_compared() {
+        let Harness { mut game, .. } = racing(5000.0);
+        let other = Maze::generate(3, 3, 99, MazeOptions::default());
+
+        game.finish_race(Some(finished_run(&other, 1000.0)));
+        assert_eq!(best_time(&game), Some(5000.0));
+        assert_eq!(game.race_margin, None);
+    }
+
+    #[test]
+    fn the_game_resumed_leaves_the_race_out_of_its_run() {
+        let Harness { mut game, time, .. } = racing(5000.0);
+        let started_at = game.race.as_ref().unwrap().run.as_ref().unwrap().started_at;
+
+        time.set(time.get() + 7000.0);
+        game.end_race().unwrap();
+        assert_eq!(game.run.as_ref().unwrap().started_at, started_at + 7000.0);
+    }
+}
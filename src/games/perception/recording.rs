@@ -0,0 +1,255 @@
+use super::{
+    board::{Board, MoveResult, Penalty},
+    maze::Maze,
+};
+use serde::{Deserialize, Serialize};
+
+/// One attempted move, stamped with milliseconds since its run began.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub(super) struct MoveRecord {
+    pub(super) at: f64,
+    pub(super) from: (usize, usize),
+    pub(super) to: (usize, usize),
+    pub(super) result: MoveResult,
+}
+
+/// Every move attempted on one maze, kept with the layout they were made on.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(super) struct Run {
+    pub(super) level: usize,
+    pub(super) maze: Maze,
+    // Cost of the wall hits in it
+    #[serde(default)]
+    pub(super) penalty: Penalty,
+    // Wall-clock start of the run, in milliseconds
+    pub(super) started_at: f64,
+    pub(super) moves: Vec<MoveRecord>,
+}
+
+impl Run {
+    pub(super) fn new(board: &Board, started_at: f64) -> Self {
+        Self {
+            level: board.level,
+            maze: board.maze.clone(),
+            penalty: board.penalty,
+            started_at,
+            moves: Vec::new(),
+        }
+    }
+
+    /// Checks that every recorded move stays inside the run's maze.
+    pub(super) fn validate(&self) -> Result<(), &'static str> {
+        self.maze.validate()?;
+        let in_maze =
+            |record: &MoveRecord| self.maze.contains(record.from) && self.maze.contains(record.to);
+        if !self.moves.iter().all(in_maze) {
+            return Err("run has a move outside its maze");
+        }
+        Ok(())
+    }
+
+    /// Logs an attempt made at wall-clock time `now`. Clicks on cells that
+    /// are not neighbours are not attempts and are left out.
+    pub(super) fn record(
+        &mut self,
+        now: f64,
+        from: (usize, usize),
+        to: (usize, usize),
+        result: MoveResult,
+    ) {
+        if result != MoveResult::NotAdjacent {
+            self.moves.push(MoveRecord {
+                at: now - self.started_at,
+                from,
+                to,
+                result,
+            });
+        }
+    }
+
+    /// Walls walked into, including one that cost the last life.
+    pub(super) fn wall_hits(&self) -> usize {
+        self.moves
+            .iter()
+            .filter(|record| matches!(record.result, MoveResult::WallHit | MoveResult::OutOfLives))
+            .count()
+    }
+
+    /// Time from the maze appearing to reaching the exit, if it was reached.
+    pub(super) fn duration(&self) -> Option<f64> {
+        self.moves
+            .last()
+            .filter(|record| record.result == MoveResult::LevelComplete)
+            .map(|record| record.at)
+    }
+}
+
+/// Plays a finished run back on a scratch board, at an adjustable speed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(super) struct Replay {
+    run: Run,
+    pub(super) board: Board,
+    // Index of the next move to apply
+    next: usize,
+    // How far into the run playback has reached, in milliseconds
+    elapsed: f64,
+    pub(super) speed: f64,
+    pub(super) playing: bool,
+}
+
+impl Replay {
+    pub(super) fn new(run: Run) -> Self {
+        Self {
+            board: Board {
+                penalty: run.penalty,
+                ..Board::new(run.maze.clone(), run.level)
+            },
+            run,
+            next: 0,
+            elapsed: 0.0,
+            speed: 1.0,
+            playing: true,
+        }
+    }
+
+    pub(super) fn finished(&self) -> bool {
+        self.next == self.run.moves.len()
+    }
+
+    /// Moves playback on by `wall_time` milliseconds, scaled by the speed,
+    /// and returns the moves that fell due. Stops playing at the end.
+    pub(super) fn advance(&mut self, wall_time: f64) -> Vec<MoveRecord> {
+        if !self.playing {
+            return Vec::new();
+        }
+        self.elapsed += wall_time * self.speed;
+
+        let mut applied = Vec::new();
+        while let Some(&record) = self.run.moves.get(self.next) {
+            if record.at > self.elapsed {
+                break;
+            }
+            self.board.try_move(record.to.0, record.to.1);
+            applied.push(record);
+            self.next += 1;
+        }
+
+        if self.finished() {
+            self.playing = false;
+        }
+        applied
+    }
+
+    pub(super) fn validate(&self) -> Result<(), &'static str> {
+        self.run.validate()?;
+        self.board.validate()?;
+        if self.next > self.run.moves.len() || !(self.speed.is_finite() && self.speed > 0.0) {
+            return Err("replay position or speed is impossible");
+        }
+        Ok(())
+    }
+
+    /// Starts over from the first move, keeping the speed.
+    pub(super) fn rewind(&mut self) {
+        *self = Self {
+            speed: self.speed,
+            ..Self::new(self.run.clone())
+        };
+    }
+}
+
+/// A retry of a completed maze against a ghost of the best run on it, with
+/// the game it interrupted set aside until it ends.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(super) struct Race {
+    pub(super) ghost: Replay,
+    pub(super) board: Board,
+    pub(super) time_remaining: i32,
+    pub(super) run: Option<Run>,
+    // Wall-clock start of the race, in milliseconds
+    pub(super) started_at: f64,
+}
+
+impl Race {
+    pub(super) fn validate(&self) -> Result<(), &'static str> {
+        self.ghost.validate()?;
+        self.board.validate()?;
+        self.run.as_ref().map_or(Ok(()), Run::validate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::perception::{
+        maze::{Maze, MazeOptions},
+        solver,
+    };
+
+    const STARTED_AT: f64 = 5000.0;
+
+    /// A run that walks the solution, one move every 100 ms.
+    fn solved_run() -> Run {
+        let mut board = Board::new(Maze::generate(6, 4, 7, MazeOptions::default()), 1);
+        let mut run = Run::new(&board, STARTED_AT);
+        let route = solver::solve(&board.maze).unwrap();
+        for (i, pair) in route.windows(2).enumerate() {
+            let (from, to) = (pair[0], pair[1]);
+            let result = board.try_move(to.0, to.1);
+            run.record(STARTED_AT + 100.0 * (i + 1) as f64, from, to, result);
+        }
+        run
+    }
+
+    #[test]
+    fn runs_log_attempts_from_their_start() {
+        let board = Board::new(Maze::generate(6, 4, 7, MazeOptions::default()), 1);
+        let mut run = Run::new(&board, STARTED_AT);
+        run.record(STARTED_AT + 250.0, (0, 0), (1, 0), MoveResult::WallHit);
+        run.record(STARTED_AT + 300.0, (0, 0), (3, 3), MoveResult::NotAdjacent);
+        run.record(STARTED_AT + 400.0, (0, 0), (0, 1), MoveResult::Moved);
+
+        let times: Vec<f64> = run.moves.iter().map(|record| record.at).collect();
+        assert_eq!(times, vec![250.0, 400.0]);
+        assert_eq!(run.wall_hits(), 1);
+        assert_eq!(run.duration(), None);
+
+        let run = solved_run();
+        assert_eq!(run.duration(), Some(100.0 * run.moves.len() as f64));
+    }
+
+    #[test]
+    fn replays_play_at_their_speed_and_stop_at_the_end() {
+        let run = solved_run();
+        let mut replay = Replay::new(run.clone());
+        replay.speed = 2.0;
+
+        // 60 ms at double speed reaches the first move, at 100 ms, only
+        let applied = replay.advance(60.0);
+        assert_eq!(applied.len(), 1);
+        assert_eq!(replay.board.current_position, run.moves[0].to);
+        assert!(replay.playing);
+
+        let applied = replay.advance(100.0 * run.moves.len() as f64);
+        assert_eq!(applied.len(), run.moves.len() - 1);
+        assert!(replay.finished());
+        assert!(!replay.playing);
+        assert_eq!(replay.board.current_position, run.moves.last().unwrap().to);
+        assert!(replay.advance(1000.0).is_empty());
+    }
+
+    #[test]
+    fn rewinding_starts_over_at_the_same_speed() {
+        let run = solved_run();
+        let mut replay = Replay::new(run.clone());
+        replay.speed = 4.0;
+        replay.advance(1_000_000.0);
+
+        replay.rewind();
+        assert!(replay.playing);
+        assert!(!replay.finished());
+        assert_eq!(replay.speed, 4.0);
+        assert_eq!(replay.board.current_position, run.maze.start_position);
+        assert_eq!(replay.advance(25.0).len(), 1);
+    }
+}
@@ -1,99 +1,152 @@
-use super::Perception;
+use super::{
+    board::Penalty, canvas::CanvasRenderer, settings::RenderMode, topology::Topology, Perception,
+};
+use crate::games::renderer::{CellView, DomRenderer, Layout, SharedRenderer};
+use std::{cell::RefCell, rc::Rc};
 use wasm_bindgen::prelude::*;
-use web_sys::Element;
+use web_sys::Document;
+
+/// The renderer for `mode`, showing only its surface. Falls back to cell
+/// elements when the page has no canvas.
+pub(super) fn renderer_for(mode: RenderMode, document: &Document) -> SharedRenderer {
+    let canvas = if mode == RenderMode::Canvas {
+        CanvasRenderer::new(document.clone())
+    } else {
+        None
+    };
+    let on_canvas = canvas.is_some();
+    let renderer: SharedRenderer = match canvas {
+        Some(canvas) => Rc::new(RefCell::new(canvas)),
+        None => Rc::new(RefCell::new(DomRenderer::new(
+            document.clone(),
+            "maze",
+            "cell",
+        ))),
+    };
+    {
+        let mut shown = renderer.borrow_mut();
+        let _ = shown.control_shown("maze", !on_canvas);
+        let _ = shown.control_shown("maze-canvas", on_canvas);
+    }
+    renderer
+}
 
 impl Perception {
     pub(crate) fn render(&self) -> Result<(), JsValue> {
-        let maze = self.document.get_element_by_id("maze").unwrap();
-
-        // Only regenerate grid if size changed
-        if maze.children().length() as usize != self.size * self.size {
-            maze.set_attribute(
-                "style",
-                &format!("grid-template-columns: repeat({}, 60px)", self.size),
-            )?;
-
-            // Clear existing content safely
-            while let Some(child) = maze.first_child() {
-                maze.remove_child(&child)?;
-            }
+        let mut renderer = self.renderer.borrow_mut();
+        let maze = &self.shown_board().maze;
 
-            // Create cells only once
-            for _ in 0..(self.size * self.size) {
-                let cell = self.document.create_element("div")?;
-                cell.set_class_name("cell");
-                let span = self.document.create_element("span")?;
-                let content = self.document.create_text_node("");
-                cell.append_child(&content)?;
-                cell.append_child(&span)?;
-                maze.append_child(&cell)?;
+        renderer.layout_changed(&self.layout())?;
+        for y in 0..maze.height {
+            for x in 0..maze.width {
+                renderer.cell_changed(maze.cell_index((x, y)), &self.cell_view((x, y)))?;
             }
         }
+        renderer.stats_changed(&self.stats())?;
+        renderer.timer_changed(&self.timer_text())?;
+        self.update_replay_controls(&mut *renderer)?;
+        self.update_race_controls(&mut *renderer)?;
+        self.update_pause_controls(&mut *renderer)
+    }
 
-        // Update existing cells
-        for y in 0..self.size {
-            for x in 0..self.size {
-                let index = (y * self.size + x) as u32;
-                if let Some(cell) = maze.children().item(index) {
-                    self.update_cell_state(&cell, x, y)?;
-                }
-            }
-        }
+    /// Marks a hinted cell until the next redraw of that cell.
+    pub(super) fn highlight_hint(&self, (x, y): (usize, usize)) -> Result<(), JsValue> {
+        let mut view = self.cell_view((x, y));
+        view.classes.push("hint".to_string());
+        let index = self.board.maze.cell_index((x, y));
+        self.renderer.borrow_mut().cell_changed(index, &view)
+    }
 
-        // Update stats
-        if let Some(level_el) = self.document.get_element_by_id("level") {
-            level_el.set_text_content(Some(&self.level.to_string()));
-        }
-        if let Some(completed_el) = self.document.get_element_by_id("moves") {
-            completed_el.set_text_content(Some(&self.moves.to_string()));
+    fn layout(&self) -> Layout {
+        let maze = &self.shown_board().maze;
+        let topology = maze.options.topology;
+        let cells = topology.cells();
+        Layout {
+            columns: (topology == Topology::Square).then_some(maze.width),
+            cells: (0..maze.height)
+                .flat_map(|y| (0..maze.width).map(move |x| cells.corners(x, y)))
+                .collect(),
+            extent: cells.extent(maze.width, maze.height),
         }
-        if let Some(timer_el) = self.document.get_element_by_id("timer") {
-            let minutes = self.time_remaining / 60;
-            let seconds = self.time_remaining % 60;
-            timer_el.set_text_content(Some(&format!("{minutes}:{seconds:02}")));
-        }
-        Ok(())
     }
 
-    pub(super) fn update_cell_state(&self, cell: &Element, x: usize, y: usize) -> Result<(), JsValue> {
-        // Reset base class
-        cell.set_class_name("cell");
-
-        // Update state classes
-        if self.visited.contains(&(x, y)) {
-            cell.class_list().add_1("visited")?;
+    fn cell_view(&self, (x, y): (usize, usize)) -> CellView {
+        let board = self.shown_board();
+        let mut classes = Vec::new();
+        if board.visited.contains(&(x, y)) {
+            classes.push("visited".to_string());
         }
-        if (x, y) == self.current_position {
-            cell.class_list().add_1("current")?;
-            // Ensure span exists for pseudo-elements
-            if cell.children().length() == 0 {
-                let span = self.document.create_element("span")?;
-                cell.append_child(&span)?;
-            }
+        if self.ghost_position() == Some((x, y)) {
+            classes.push("ghost".to_string());
+        }
+        if (x, y) == board.current_position {
+            classes.push("current".to_string());
         }
 
-        // Update content
-        let content = if (x, y) == self.key_position && !self.has_key
-            || (x, y) == self.current_position && self.has_key
-        {
-            "🔑"
-        } else if (x, y) == self.door_position {
-            "🚪"
+        // Items are tinted by colour when there are several pairs
+        let (content, colour) = self.cell_content((x, y));
+        if let Some(colour) = colour {
+            classes.push(format!("colour-{colour}"));
         }
-        else if (x, y) == self.current_position && !self.has_key {
-            "👤"
+        CellView {
+            text: content.to_string(),
+            classes,
         }
-        else {
-            ""
+    }
+
+    /// Emoji shown in a cell, and the key/door pair colour to tint it with
+    /// when the maze has several pairs.
+    fn cell_content(&self, (x, y): (usize, usize)) -> (&'static str, Option<usize>) {
+        let board = self.shown_board();
+        let key = board
+            .maze
+            .key_at((x, y))
+            .filter(|key| !board.inventory.contains(key));
+        let (content, colour) = if let Some(key) = key {
+            ("🔑", Some(key))
+        } else if (x, y) == board.current_position {
+            match board.inventory.last() {
+                Some(&held) => ("🔑", Some(held)),
+                None => ("👤", None),
+            }
+        } else if let Some(door) = board.maze.door_at((x, y)) {
+            ("🚪", Some(door))
+        } else {
+            ("", None)
         };
+        (content, colour.filter(|_| board.maze.keys.len() > 1))
+    }
 
-        // Update text content if it's different
-        if let Some(first_child) = cell.first_child() {
-            if first_child.text_content().unwrap_or_default() != content {
-                first_child.set_text_content(Some(content));
+    fn stats(&self) -> Vec<(&'static str, String)> {
+        let board = self.shown_board();
+        let efficiency = board
+            .last_efficiency
+            .map_or_else(|| "–".to_string(), |e| format!("{e}%"));
+        let penalty = match board.penalty {
+            Penalty::Restart => "back to start".to_string(),
+            Penalty::Checkpoint => "back to last key".to_string(),
+            Penalty::Time(seconds) => format!("−{seconds}s"),
+            Penalty::Lives(lives) => {
+                format!("♥ {}/{lives}", lives.saturating_sub(board.lives_lost))
             }
-        }
+        };
+        vec![
+            ("level", board.level.to_string()),
+            ("moves", board.moves.to_string()),
+            (
+                "keys",
+                format!("{}/{}", board.inventory.len(), board.maze.keys.len()),
+            ),
+            ("hints", board.hints.to_string()),
+            ("par", board.maze.par.to_string()),
+            ("efficiency", efficiency),
+            ("penalty", penalty),
+        ]
+    }
 
-        Ok(())
+    pub(super) fn timer_text(&self) -> String {
+        let minutes = self.time_remaining / 60;
+        let seconds = self.time_remaining % 60;
+        format!("{minutes}:{seconds:02}")
     }
 }
@@ -0,0 +1,148 @@
+use super::{
+    board::{Board, MoveResult},
+    recording::{Replay, Run},
+    Perception,
+};
+use crate::games::renderer::Renderer;
+use std::{cell::RefCell, rc::Rc};
+use wasm_bindgen::prelude::*;
+
+// How often replays, ghosts and flashes are advanced and redrawn, in milliseconds
+const FRAME_INTERVAL: i32 = 50;
+// Playback speeds the speed button cycles through
+const SPEEDS: [f64; 4] = [0.5, 1.0, 2.0, 4.0];
+
+type Action = fn(&mut Perception) -> Result<(), JsValue>;
+
+impl Perception {
+    /// Drives replays, race ghosts and canvas wall flashes, and hooks up the
+    /// replay and race buttons.
+    pub(super) fn setup_replay(game_state: Rc<RefCell<Perception>>) -> Result<(), JsValue> {
+        let window = web_sys::window().unwrap();
+        let performance = window.performance().unwrap();
+        let mut last_frame = performance.now();
+
+        let ticker = {
+            let game_state = game_state.clone();
+            Closure::wrap(Box::new(move || {
+                let now = performance.now();
+                let wall_time = now - last_frame;
+                last_frame = now;
+                if let Ok(mut game) = game_state.try_borrow_mut() {
+                    if game.paused_at.is_none() {
+                        game.step_replay(wall_time).unwrap();
+                        game.step_ghost(wall_time).unwrap();
+                    }
+                    game.renderer.borrow_mut().tick(now).unwrap();
+                }
+            }) as Box<dyn FnMut()>)
+        };
+        window.set_interval_with_callback_and_timeout_and_arguments_0(
+            ticker.as_ref().unchecked_ref(),
+            FRAME_INTERVAL,
+        )?;
+        ticker.forget();
+
+        let buttons: [(&str, Action); 6] = [
+            ("replay", Perception::replay),
+            ("replay-toggle", Perception::toggle_replay),
+            ("replay-speed", Perception::cycle_replay_speed),
+            ("replay-stop", Perception::stop_replay),
+            ("race", Perception::race_best),
+            ("race-stop", Perception::end_race),
+        ];
+        let document = game_state.borrow().page()?;
+        for (id, action) in buttons {
+            if let Some(button) = document.get_element_by_id(id) {
+                let game_state = game_state.clone();
+                let handler = Closure::wrap(Box::new(move |_event: web_sys::MouseEvent| {
+                    if let Ok(mut game) = game_state.try_borrow_mut() {
+                        action(&mut game).unwrap();
+                    }
+                }) as Box<dyn FnMut(_)>);
+
+                button
+                    .add_event_listener_with_callback("click", handler.as_ref().unchecked_ref())?;
+                handler.forget();
+            }
+        }
+        Ok(())
+    }
+
+    /// Shows `run` played back in place of the game, whose clock stands
+    /// still until the replay closes.
+    pub(super) fn open_replay(&mut self, run: Run) {
+        if self.replay.is_none() {
+            self.replay_opened_at = self.now();
+        }
+        self.replay = Some(Replay::new(run));
+    }
+
+    /// Closes any replay and moves the run's start on by the time it was
+    /// open, as a pause does, so the run's times leave it out.
+    pub(super) fn close_replay(&mut self) {
+        if self.replay.take().is_some() {
+            let now = self.now();
+            if let Some(run) = self.run.as_mut() {
+                run.started_at += now - self.replay_opened_at;
+            }
+        }
+    }
+
+    fn step_replay(&mut self, wall_time: f64) -> Result<(), JsValue> {
+        let Some(replay) = self.replay.as_mut() else {
+            return Ok(());
+        };
+        let applied = replay.advance(wall_time);
+        if applied.is_empty() {
+            return Ok(());
+        }
+
+        self.render()?;
+        for record in applied {
+            if record.result == MoveResult::WallHit {
+                self.animate_wall_hit(record.from, record.to)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn cycle_replay_speed(&mut self) -> Result<(), JsValue> {
+        let speed = self.replay.as_ref().map_or(1.0, |replay| replay.speed);
+        let next = SPEEDS
+            .iter()
+            .copied()
+            .find(|&s| s > speed)
+            .unwrap_or(SPEEDS[0]);
+        self.set_replay_speed(next)
+    }
+
+    /// Shows the replay buttons that apply to the current state.
+    pub(super) fn update_replay_controls(
+        &self,
+        renderer: &mut dyn Renderer,
+    ) -> Result<(), JsValue> {
+        let replay = self.replay.as_ref();
+        let idle = replay.is_none() && self.race.is_none();
+        renderer.control_shown("replay", idle && self.last_run.is_some())?;
+        renderer.control_shown("replay-toggle", replay.is_some())?;
+        renderer.control_shown("replay-speed", replay.is_some())?;
+        renderer.control_shown("replay-stop", replay.is_some())?;
+
+        if let Some(replay) = replay {
+            let toggle = if replay.playing { "Pause" } else { "Play" };
+            renderer.stats_changed(&[
+                ("replay-toggle", toggle.to_string()),
+                ("replay-speed", format!("{}×", replay.speed)),
+            ])?;
+        }
+        Ok(())
+    }
+
+    /// The board on screen: the replay's while one is open, else the game's.
+    pub(super) fn shown_board(&self) -> &Board {
+        self.replay
+            .as_ref()
+            .map_or(&self.board, |replay| &replay.board)
+    }
+}
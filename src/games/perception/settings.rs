@@ -0,0 +1,228 @@
+use super::{adaptive::Adaptive, board::Penalty, difficulty::Curve};
+use crate::games::storage::SharedStorage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+/// Direction of a keyboard (or other non-pointer) move. Diagonals are for hex
+/// and triangle cells, whose neighbours do not all line up with the axes.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Direction {
+    /// Unit vector in screen axes (y grows downwards).
+    pub(super) fn vector(self) -> (f64, f64) {
+        let d = std::f64::consts::FRAC_1_SQRT_2;
+        match self {
+            Self::Up => (0.0, -1.0),
+            Self::Down => (0.0, 1.0),
+            Self::Left => (-1.0, 0.0),
+            Self::Right => (1.0, 0.0),
+            Self::UpLeft => (-d, -d),
+            Self::UpRight => (d, -d),
+            Self::DownLeft => (-d, d),
+            Self::DownRight => (d, d),
+        }
+    }
+}
+
+/// How the maze is drawn: one element per cell, or a single canvas that
+/// stays quick on large mazes.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RenderMode {
+    #[default]
+    Dom,
+    Canvas,
+}
+
+/// What walking into a wall costs, as set from JavaScript.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WallPenalty {
+    /// Back to the start, dropping every key.
+    ResetToStart,
+    /// Back to where the last key was picked up, keeping the keys.
+    ResetToCheckpoint,
+    /// Some seconds off the clock.
+    LoseTime,
+    /// One of a few lives a maze; losing the last fails the level.
+    LoseLife,
+}
+
+/// What becomes of saved progress that has been left for a while.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(super) struct SaveExpiry {
+    // Seconds a save keeps its maze and clock
+    pub(super) after: u32,
+    // Levels an older save drops on the fresh maze it starts
+    pub(super) decay: usize,
+}
+
+impl SaveExpiry {
+    /// The same expiry, kept to between a minute and a week.
+    pub(super) fn clamped(self) -> Self {
+        Self {
+            after: self.after.clamp(60, 604_800),
+            ..self
+        }
+    }
+}
+
+impl Default for SaveExpiry {
+    fn default() -> Self {
+        Self {
+            after: 300,
+            decay: 0,
+        }
+    }
+}
+
+/// Player preferences, kept apart from the maze state so they survive resets.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(super) struct Settings {
+    // `KeyboardEvent.code` → direction, so WASD stays put on other layouts
+    #[serde(default = "default_key_bindings")]
+    pub(super) key_bindings: HashMap<String, Direction>,
+    // Shortest drag, in CSS pixels, that counts as a swipe rather than a tap
+    #[serde(default = "default_swipe_distance")]
+    pub(super) swipe_distance: f64,
+    // Longest drag, in milliseconds, that still counts as a swipe
+    #[serde(default = "default_swipe_duration")]
+    pub(super) swipe_duration: f64,
+    // Stick deflection, from 0 to 1, below which a gamepad stick is ignored
+    #[serde(default = "default_gamepad_dead_zone")]
+    pub(super) gamepad_dead_zone: f64,
+    #[serde(default)]
+    pub(super) render_mode: RenderMode,
+    // Cost of a wall hit, for every new game
+    #[serde(default)]
+    pub(super) wall_penalty: Penalty,
+    // Size, time limit and more for each level
+    #[serde(default)]
+    pub(super) difficulty: Curve,
+    #[serde(default)]
+    pub(super) save_expiry: SaveExpiry,
+    #[serde(default)]
+    pub(super) adaptive: Adaptive,
+}
+
+fn default_swipe_distance() -> f64 {
+    30.0
+}
+
+fn default_swipe_duration() -> f64 {
+    600.0
+}
+
+fn default_gamepad_dead_zone() -> f64 {
+    0.35
+}
+
+fn default_key_bindings() -> HashMap<String, Direction> {
+    [
+        ("ArrowUp", Direction::Up),
+        ("ArrowDown", Direction::Down),
+        ("ArrowLeft", Direction::Left),
+        ("ArrowRight", Direction::Right),
+        ("KeyW", Direction::Up),
+        ("KeyS", Direction::Down),
+        ("KeyA", Direction::Left),
+        ("KeyD", Direction::Right),
+        ("KeyQ", Direction::UpLeft),
+        ("KeyE", Direction::UpRight),
+        ("KeyZ", Direction::DownLeft),
+        ("KeyC", Direction::DownRight),
+    ]
+    .iter()
+    .map(|&(code, direction)| (code.to_string(), direction))
+    .collect()
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            key_bindings: default_key_bindings(),
+            swipe_distance: default_swipe_distance(),
+            swipe_duration: default_swipe_duration(),
+            gamepad_dead_zone: default_gamepad_dead_zone(),
+            render_mode: RenderMode::default(),
+            wall_penalty: Penalty::default(),
+            difficulty: Curve::default(),
+            save_expiry: SaveExpiry::default(),
+            adaptive: Adaptive::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Loads the settings saved in `storage`, falling back to the defaults.
+    pub(super) fn load(storage: &SharedStorage) -> Self {
+        let mut settings: Self = storage
+            .borrow()
+            .get_item("maze_settings")
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        if settings.difficulty.validate().is_err() {
+            settings.difficulty = Curve::default();
+        }
+        // As `set_wall_penalty` and `set_save_expiry` would have
+        settings.wall_penalty = settings.wall_penalty.clamped();
+        settings.save_expiry = settings.save_expiry.clamped();
+        settings
+    }
+
+    pub(super) fn save(&self, storage: &SharedStorage) -> Result<(), JsValue> {
+        let json = serde_json::to_string(self).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        storage.borrow_mut().set_item("maze_settings", &json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::{perception::board::MAX_LIVES, storage::MemoryStorage};
+    use std::{cell::RefCell, rc::Rc};
+
+    fn load(json: &str) -> Settings {
+        let storage: SharedStorage = Rc::new(RefCell::new(MemoryStorage::default()));
+        storage
+            .borrow_mut()
+            .set_item("maze_settings", json)
+            .unwrap();
+        Settings::load(&storage)
+    }
+
+    #[test]
+    fn loaded_wall_penalties_are_clamped() {
+        let settings = load(r#"{ "wall_penalty": { "Time": 0 } }"#);
+        assert_eq!(settings.wall_penalty, Penalty::Time(1));
+        let settings = load(r#"{ "wall_penalty": { "Lives": 100 } }"#);
+        assert_eq!(settings.wall_penalty, Penalty::Lives(MAX_LIVES));
+    }
+
+    #[test]
+    fn loaded_save_expiries_are_clamped() {
+        let settings = load(r#"{ "save_expiry": { "after": 0, "decay": 2 } }"#);
+        assert_eq!(
+            settings.save_expiry,
+            SaveExpiry {
+                after: 60,
+                decay: 2
+            }
+        );
+        let settings = load(r#"{ "save_expiry": { "after": 4000000000 } }"#);
+        assert_eq!(settings.save_expiry.after, 604_800);
+    }
+}
@@ -0,0 +1,123 @@
+use super::maze::Maze;
+use std::collections::VecDeque;
+
+/// Shortest route from `from`, already holding the keys in `inventory`,
+/// through the exit. Searches over (cell, keys held) so locked doors and key
+/// order are respected.
+fn route(maze: &Maze, from: (usize, usize), inventory: &[usize]) -> Option<Vec<(usize, usize)>> {
+    let grid = maze.grid();
+    let exit = maze.exit();
+    let held = inventory.iter().fold(0, |mask, &key| mask | 1 << key);
+    let states = 1 << maze.keys.len();
+    let state = |cell: usize, mask: usize| cell * states + mask;
+
+    let mut parent = vec![None; grid.cell_count() * states];
+    let mut seen = vec![false; grid.cell_count() * states];
+    let mut queue = VecDeque::new();
+    let first = state(grid.cell(from), held);
+    seen[first] = true;
+    queue.push_back((grid.cell(from), held));
+
+    while let Some((cell, mask)) = queue.pop_front() {
+        for neighbour in grid.open_neighbours(cell) {
+            let position = grid.position(neighbour);
+            let door = maze.door_at(position);
+            if door.is_some_and(|door| mask & 1 << door == 0) {
+                continue;
+            }
+            let mut next_mask = mask;
+            if let Some(key) = maze.key_at(position) {
+                // Ordered keys only pick up once every earlier key is held.
+                if !maze.options.ordered_keys || mask == (1 << key) - 1 {
+                    next_mask |= 1 << key;
+                }
+            }
+
+            let next = state(neighbour, next_mask);
+            if seen[next] {
+                continue;
+            }
+            seen[next] = true;
+            parent[next] = Some(state(cell, mask));
+            if door == Some(exit) {
+                let mut path = vec![position];
+                let mut current = next;
+                while let Some(p) = parent[current] {
+                    path.push(grid.position(p / states));
+                    current = p;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            queue.push_back((neighbour, next_mask));
+        }
+    }
+    None
+}
+
+/// Optimal route from the start, collecting keys, through the exit.
+pub(super) fn solve(maze: &Maze) -> Option<Vec<(usize, usize)>> {
+    route(maze, maze.start_position, &[])
+}
+
+/// Next cell on the optimal route from `from` with the keys in `inventory`,
+/// following the maze's current walls.
+pub(super) fn next_step(
+    maze: &Maze,
+    from: (usize, usize),
+    inventory: &[usize],
+) -> Option<(usize, usize)> {
+    route(maze, from, inventory)?.get(1).copied()
+}
+
+/// Number of moves in the optimal route, or 0 if the maze cannot be solved.
+pub(super) fn par(maze: &Maze) -> usize {
+    solve(maze).map_or(0, |route| route.len() - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::perception::maze::tests::sample_mazes;
+
+    #[test]
+    fn routes_follow_open_passages_from_the_start_to_the_exit() {
+        for maze in sample_mazes() {
+            let route = solve(&maze).unwrap();
+            assert_eq!(route[0], maze.start_position);
+            assert_eq!(maze.door_at(*route.last().unwrap()), Some(maze.exit()));
+            for step in route.windows(2) {
+                assert!(maze.is_adjacent(step[0], step[1]));
+                assert!(!maze.has_wall(step[0], step[1]));
+            }
+            assert_eq!(route.len() - 1, maze.par);
+        }
+    }
+
+    #[test]
+    fn routes_pass_each_key_before_its_door() {
+        for maze in sample_mazes() {
+            let route = solve(&maze).unwrap();
+            let first_visit = |cell| route.iter().position(|&step| step == cell).unwrap();
+            for (key, door) in maze.keys.iter().zip(&maze.doors) {
+                assert!(first_visit(*key) < first_visit(*door));
+            }
+        }
+    }
+
+    #[test]
+    fn hints_follow_the_route() {
+        for maze in sample_mazes().take(50) {
+            let route = solve(&maze).unwrap();
+            assert_eq!(next_step(&maze, maze.start_position, &[]), Some(route[1]));
+        }
+    }
+
+    #[test]
+    fn a_walled_in_start_has_no_route() {
+        let mut maze = sample_mazes().next().unwrap();
+        maze.walls.iter_mut().for_each(|wall| *wall = true);
+        assert_eq!(solve(&maze), None);
+        assert_eq!(par(&maze), 0);
+    }
+}
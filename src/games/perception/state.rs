@@ -1,18 +1,426 @@
-use super::Perception;
-use js_sys::Date;
+//! Saved games. A save is the `Perception` state as JSON, tagged with the
+//! schema `version` it was written in. Older saves are upgraded one version
+//! at a time before being read, then checked for consistency.
+//!
+//! | version | layout                                                      |
+//! |---------|-------------------------------------------------------------|
+//! | 0       | untagged; the first game's square maze, field by field      |
+//! | 1       | the maze saved as its share `code`, with its `options`      |
+
+use super::{
+    maze::{Maze, MazeOptions},
+    settings::Settings,
+    Perception,
+};
+use crate::games::{clock::Clock, storage::SharedStorage};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
 use wasm_bindgen::prelude::*;
 
+const SAVE_VERSION: u64 = 1;
+
+type Object = Map<String, Value>;
+
+/// Upgrades, in order, a save from version `i` to version `i + 1`.
+const MIGRATIONS: [fn(&mut Object); SAVE_VERSION as usize] = [v0_to_v1];
+
+/// Why a save could not be restored.
+#[derive(Debug)]
+pub(super) enum SaveError {
+    Unreadable(serde_json::Error),
+    NotAnObject,
+    BadVersion,
+    TooNew(u64),
+    Inconsistent(&'static str),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unreadable(e) => write!(f, "save is not readable: {e}"),
+            Self::NotAnObject => write!(f, "save is not a JSON object"),
+            Self::BadVersion => write!(f, "save has a malformed version"),
+            Self::TooNew(v) => write!(f, "save version {v} is newer than this game"),
+            Self::Inconsistent(reason) => write!(f, "save is inconsistent: {reason}"),
+        }
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Unreadable(e)
+    }
+}
+
+#[derive(Serialize)]
+struct Save<'a> {
+    version: u64,
+    #[serde(flatten)]
+    game: &'a Perception,
+}
+
 impl Perception {
+    /// Saves the game in its storage, unless it is a shared maze's.
     pub(super) fn save_state(&self) -> Result<(), JsValue> {
-        let window = web_sys::window().expect("no global window exists");
-        let storage = window.local_storage()?.expect("no local storage exists");
-
-        let state = serde_wasm_bindgen::to_value(&self)?;
-        let state_json = js_sys::JSON::stringify(&state)?.as_string().unwrap();
+        if self.unsaved {
+            return Ok(());
+        }
+        let save = Save {
+            version: SAVE_VERSION,
+            game: self,
+        };
+        let state_json =
+            serde_json::to_string(&save).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let mut storage = self.storage.borrow_mut();
         storage.set_item("maze_state", &state_json)?;
-        storage.set_item("maze_time", &Date::now().to_string())?;
-        storage.set_item("maze_level", &self.level.to_string())?;
+        storage.set_item("maze_time", &self.now().to_string())?;
+        storage.set_item("maze_level", &self.game_level().to_string())?;
 
         Ok(())
     }
+
+    /// The game saved under `maze_state` in `storage`, with its timer
+    /// caught up to `now` on `clock` unless it was saved paused. None when
+    /// there is no save. A save older than the player's `save_expiry`
+    /// setting starts a fresh maze at its level less the setting's decay,
+    /// and one whose timer has run out since starts a fresh maze at its
+    /// level. The game is off the page until `on_page`.
+    pub(super) fn restore(
+        storage: SharedStorage,
+        clock: Clock,
+        now: f64,
+    ) -> Result<Option<Self>, SaveError> {
+        let (state, last_save) = {
+            let storage = storage.borrow();
+            let Some(state) = storage.get_item("maze_state") else {
+                return Ok(None);
+            };
+            let last_save = storage
+                .get_item("maze_time")
+                .and_then(|time| time.parse::<f64>().ok())
+                .unwrap_or(0.0);
+            (state, last_save)
+        };
+
+        let mut game = Self::from_save(&state)?;
+        game.settings = Settings::load(&storage);
+        game.storage = storage;
+        game.clock = clock;
+        let expiry = game.settings.save_expiry;
+        if now - last_save > f64::from(expiry.after) * 1000.0 {
+            let level = game.game_level().saturating_sub(expiry.decay).max(1);
+            game.start_fresh(level, now);
+            return Ok(Some(game));
+        }
+        // A paused game's clock stopped when it was paused
+        if game.paused_at.is_some() {
+            return Ok(Some(game));
+        }
+        let now_secs = now / 1000.0;
+        let elapsed = (now_secs - game.last_tick) as i32;
+        if elapsed >= game.time_remaining {
+            game.start_fresh(game.game_level(), now);
+            return Ok(Some(game));
+        }
+        game.time_remaining -= elapsed;
+        game.last_tick = now_secs;
+        Ok(Some(game))
+    }
+
+    /// The level last saved under `maze_level` in `storage`, for when the
+    /// save itself cannot be read.
+    pub(super) fn saved_level(storage: &SharedStorage) -> Option<usize> {
+        storage
+            .borrow()
+            .get_item("maze_level")?
+            .parse()
+            .ok()
+            .filter(|&level| level >= 1)
+    }
+
+    /// Puts the game at level `number` on a fresh maze with a full clock,
+    /// dropping any race, replay or pause. The session's options, runs and
+    /// best run are kept.
+    pub(super) fn start_fresh(&mut self, number: usize, now: f64) {
+        self.resume_game();
+        self.replay = None;
+        self.paused_at = None;
+        let level = self.level_setup(number);
+        self.board.restart_at_level(number, &level);
+        self.time_remaining = self.time_limit_for(&level);
+        self.last_tick = now / 1000.0;
+        self.begin_run();
+    }
+
+    /// The level of the game being played, set aside during a race.
+    fn game_level(&self) -> usize {
+        self.race
+            .as_ref()
+            .map_or(self.board.level, |race| race.board.level)
+    }
+
+    /// Reads a save of any known version.
+    fn from_save(json: &str) -> Result<Self, SaveError> {
+        let mut save: Value = serde_json::from_str(json)?;
+        let object = save.as_object_mut().ok_or(SaveError::NotAnObject)?;
+        let version = match object.remove("version") {
+            None => 0,
+            Some(version) => version.as_u64().ok_or(SaveError::BadVersion)?,
+        };
+        if version > SAVE_VERSION {
+            return Err(SaveError::TooNew(version));
+        }
+        for migrate in &MIGRATIONS[version as usize..] {
+            migrate(object);
+        }
+
+        let game: Self = serde_json::from_value(save)?;
+        game.validate().map_err(SaveError::Inconsistent)?;
+        Ok(game)
+    }
+
+    fn validate(&self) -> Result<(), &'static str> {
+        self.board.validate()?;
+        let runs = [&self.run, &self.last_run, &self.best_run];
+        for run in runs.iter().copied().flatten() {
+            run.validate()?;
+        }
+        self.race.as_ref().map_or(Ok(()), |race| race.validate())
+    }
+}
+
+/// The first game's save, before saves were tagged: a square maze of
+/// `size` with one key and door, held field by field beside the board.
+#[derive(Deserialize)]
+struct FirstSave {
+    size: usize,
+    walls: Vec<bool>,
+    start_position: (usize, usize),
+    key_position: (usize, usize),
+    door_position: (usize, usize),
+    #[serde(default)]
+    has_key: bool,
+}
+
+/// Replaces the first game's maze fields with the maze, saved as its code,
+/// and its key with an inventory. A maze that cannot be read is left for
+/// reading the save to reject.
+fn v0_to_v1(save: &mut Object) {
+    let Ok(first) = serde_json::from_value::<FirstSave>(Value::Object(save.clone())) else {
+        return;
+    };
+    let positions = [
+        first.start_position,
+        first.key_position,
+        first.door_position,
+    ];
+    let in_maze = positions
+        .iter()
+        .all(|&(x, y)| x < first.size && y < first.size);
+    if first.walls.len() != first.size * first.size * 4 || !in_maze {
+        return;
+    }
+    // The single key was key 0
+    let inventory: Vec<usize> = if first.has_key { vec![0] } else { Vec::new() };
+    let maze = Maze {
+        width: first.size,
+        height: first.size,
+        seed: 0,
+        options: MazeOptions::default(),
+        walls: first.walls,
+        start_position: first.start_position,
+        keys: vec![first.key_position],
+        doors: vec![first.door_position],
+        par: 0,
+    };
+    let Ok(maze) = serde_json::to_value(&maze) else {
+        return;
+    };
+
+    let fields = [
+        "size",
+        "walls",
+        "start_position",
+        "key_position",
+        "door_position",
+        "has_key",
+    ];
+    for field in fields {
+        save.remove(field);
+    }
+    save.insert("maze".to_string(), maze);
+    save.insert("inventory".to_string(), Value::from(inventory));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::{
+        perception::{
+            board::Board,
+            maze::MAX_SIZE,
+            tests::{headless, Harness},
+        },
+        renderer::RecordingRenderer,
+        storage::MemoryStorage,
+    };
+    use serde_json::json;
+    use std::{cell::RefCell, rc::Rc};
+
+    /// A game on one of the largest mazes a level can have, with a run,
+    /// a last run and a best run on it.
+    fn game_on_a_large_maze() -> Perception {
+        let mut game = Perception::headless(
+            1,
+            Rc::new(RefCell::new(RecordingRenderer::default())),
+            Rc::new(RefCell::new(MemoryStorage::default())),
+            Rc::new(|| 0.0),
+        )
+        .unwrap();
+        let maze = Maze::generate(MAX_SIZE, MAX_SIZE, 7, MazeOptions::default());
+        game.board = Board::new(maze, 1);
+        game.begin_run();
+        game.last_run = game.run.clone();
+        game.best_run = game.run.clone();
+        game
+    }
+
+    /// A game saved at level `level`, with saves expiring after `after`
+    /// seconds and dropping `decay` levels then.
+    fn saved_at_level(level: usize, after: u32, decay: usize) -> Harness {
+        let mut harness = headless(11);
+        let game = &mut harness.game;
+        game.set_save_expiry(after, decay).unwrap();
+        game.board.level = level;
+        game.save_state().unwrap();
+        harness
+    }
+
+    /// The game `harness` saved, restored `seconds` after it was saved.
+    fn restore_after(harness: &Harness, seconds: f64) -> Perception {
+        let now = harness.time.get() + seconds * 1000.0;
+        let storage = harness.storage.clone();
+        Perception::restore(storage, harness.game.clock.clone(), now)
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn expired_saves_start_fresh_levels_lower() {
+        let harness = saved_at_level(5, 60, 2);
+        let restored = restore_after(&harness, 61.0);
+        assert_eq!(restored.board.level, 3);
+        assert_ne!(restored.board.maze, harness.game.board.maze);
+        assert_eq!(restored.board.moves, 0);
+
+        let harness = saved_at_level(2, 60, 5);
+        assert_eq!(restore_after(&harness, 61.0).board.level, 1);
+    }
+
+    #[test]
+    fn unexpired_saves_carry_on_with_the_time_gone_taken_off() {
+        let harness = saved_at_level(5, 60, 2);
+        let restored = restore_after(&harness, 10.0);
+        assert_eq!(restored.board.level, 5);
+        assert_eq!(restored.board.maze, harness.game.board.maze);
+        assert_eq!(restored.time_remaining, harness.game.time_remaining - 10);
+    }
+
+    #[test]
+    fn saves_whose_timer_ran_out_start_the_level_afresh() {
+        let mut harness = saved_at_level(4, 600, 2);
+        harness.game.time_remaining = 5;
+        harness.game.save_state().unwrap();
+
+        let restored = restore_after(&harness, 10.0);
+        assert_eq!(restored.board.level, 4);
+        assert_ne!(restored.board.maze, harness.game.board.maze);
+        let level = restored.level_setup(4);
+        assert_eq!(restored.time_remaining, restored.time_limit_for(&level));
+    }
+
+    fn save_json(game: &Perception) -> String {
+        serde_json::to_string(&Save {
+            version: SAVE_VERSION,
+            game,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn saves_hold_mazes_as_codes() {
+        let game = game_on_a_large_maze();
+        let json = save_json(&game);
+
+        // Four copies of the walls as JSON would take over a megabyte
+        assert!(json.len() < 100_000, "save is {} bytes", json.len());
+        assert!(!json.contains("walls"));
+
+        let restored = Perception::from_save(&json).unwrap();
+        assert_eq!(restored.board.maze, game.board.maze);
+        assert_eq!(restored.best_run.unwrap().maze, game.board.maze);
+    }
+
+    #[test]
+    fn saves_from_the_single_key_game_are_read() {
+        let maze = Maze::generate(2, 2, 9, MazeOptions::default());
+        let (key, door) = (maze.keys[0], maze.doors[0]);
+        // As the first version of the game wrote `maze_state`: a square
+        // maze of `size`, one key and door, and no version tag
+        let save = json!({
+            "size": 2,
+            "level": 3,
+            "moves": 4,
+            "walls": maze.walls,
+            "current_position": key,
+            "start_position": maze.start_position,
+            "key_position": key,
+            "door_position": door,
+            "visited": [maze.start_position, key],
+            "has_key": true,
+            "time_remaining": 42,
+            "last_tick": 1_700_000_000.0,
+        });
+
+        let game = Perception::from_save(&save.to_string()).unwrap();
+
+        let board = &game.board;
+        assert_eq!((board.level, board.moves), (3, 4));
+        assert_eq!((board.maze.width, board.maze.height), (2, 2));
+        assert_eq!(board.maze.walls, maze.walls);
+        assert_eq!(board.maze.start_position, maze.start_position);
+        assert_eq!(board.maze.keys, [key]);
+        assert_eq!(board.maze.doors, [door]);
+        assert_eq!(board.current_position, key);
+        assert_eq!(board.inventory, [0]);
+        assert_eq!(game.time_remaining, 42);
+    }
+
+    #[test]
+    fn first_version_saves_with_a_broken_maze_are_refused() {
+        let save = json!({
+            "size": 2,
+            "level": 1,
+            "walls": vec![true; 12],
+            "current_position": [0, 0],
+            "start_position": [0, 0],
+            "key_position": [1, 0],
+            "door_position": [1, 1],
+            "visited": [[0, 0]],
+            "has_key": false,
+            "time_remaining": 42,
+            "last_tick": 1_700_000_000.0,
+        });
+        assert!(Perception::from_save(&save.to_string()).is_err());
+    }
+
+    #[test]
+    fn saves_from_a_newer_game_are_refused() {
+        let save = json!({ "version": SAVE_VERSION + 1 });
+        assert!(matches!(
+            Perception::from_save(&save.to_string()),
+            Err(SaveError::TooNew(version)) if version == SAVE_VERSION + 1
+        ));
+    }
 }
@@ -1,4 +1,4 @@
-use super::Perception;
+use super::{adaptive::Outcome, Perception};
 use std::{cell::RefCell, rc::Rc};
 use wasm_bindgen::prelude::*;
 use web_sys::console;
@@ -9,7 +9,7 @@ impl Perception {
             let game_state = game_state.clone();
             Closure::wrap(Box::new(move || {
                 if let Ok(mut game) = game_state.try_borrow_mut() {
-                    let now = js_sys::Date::now() / 1000.0;
+                    let now = game.now() / 1000.0;
                     let delta = (now - game.last_tick) as i32;
                     if delta >= 1 {
                         game.update_timer(now);
@@ -36,11 +36,14 @@ impl Perception {
     }
 
     fn update_timer(&mut self, now: f64) {
-        self.time_remaining -= 1;
+        // The clock stands still while paused or a replay is on screen
+        if self.replay.is_none() && self.paused_at.is_none() {
+            self.time_remaining -= 1;
+        }
         self.last_tick = now;
 
         if self.time_remaining <= 0 {
-            self.reset_on_timeout(now);
+            self.fail_level(now);
         }
 
         self.update_timer_display();
@@ -50,21 +53,34 @@ impl Perception {
     }
 
     fn update_timer_display(&self) {
-        if let Some(timer_el) = self.document.get_element_by_id("timer") {
-            let minutes = self.time_remaining / 60;
-            let seconds = self.time_remaining % 60;
-            timer_el.set_text_content(Some(&format!("{minutes}:{seconds:02}")));
-        }
+        let _ = self.renderer.borrow_mut().timer_changed(&self.timer_text());
     }
 
-    fn reset_on_timeout(&mut self, now: f64) {
-        let new_game = Self::create_maze(self.size, self.document.clone());
-        self.walls = new_game.walls;
-        self.key_position = new_game.key_position;
-        self.door_position = new_game.door_position;
-        self.reset_position();
-        self.time_remaining = 300;
+    /// Ends the level unfinished, on a timeout or the last life lost: the
+    /// level starts over on a fresh maze, or the adaptive controller picks
+    /// an easier one.
+    pub(super) fn fail_level(&mut self, now: f64) {
+        // A race that is failed is abandoned
+        if self.race.is_some() {
+            self.resume_game();
+            self.render().unwrap();
+            return;
+        }
+        let outcome = if self.time_remaining <= 0 {
+            Outcome::TimedOut
+        } else {
+            Outcome::OutOfLives
+        };
+        let number = self.level_after(outcome);
+        let level = self.level_setup(number);
+        if number == self.board.level {
+            self.board.regenerate(&level);
+        } else {
+            self.board.restart_at_level(number, &level);
+        }
+        self.time_remaining = self.time_limit_for(&level);
         self.last_tick = now;
+        self.begin_run();
         self.render().unwrap();
     }
 }
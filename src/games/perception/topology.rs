@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+// Height of a row of unit-width hexagons or triangles, as a multiple of width.
+const HEX_ROW: f64 = 0.866_025_403_784_438_6; // √3 / 2
+const TRIANGLE_ROW: f64 = HEX_ROW;
+
+/// Shape of a maze's cells and how they connect. Walls of a cell are numbered
+/// in the order of its corners.
+pub(super) trait CellTopology {
+    fn walls_per_cell(&self) -> usize;
+
+    /// The cell across `wall` of `(x, y)`, which may be out of bounds, and the
+    /// index of the same wall on that side.
+    fn across(&self, x: isize, y: isize, wall: usize) -> ((isize, isize), usize);
+
+    /// Corners of the cell in maze units (cells are one unit wide), such that
+    /// wall `i` runs from corner `i` to corner `i + 1`.
+    fn corners(&self, x: usize, y: usize) -> Vec<(f64, f64)>;
+
+    /// Drawing size of a `width` × `height` maze, in the same units.
+    fn extent(&self, width: usize, height: usize) -> (f64, f64);
+
+    /// The cell whose row and column a point in maze units falls in, which
+    /// may be out of bounds. For cells that do not tile in a plain grid the
+    /// point may instead lie in one of the eight cells around it.
+    fn near(&self, point: (f64, f64)) -> (isize, isize);
+}
+
+/// Cell shape, selectable from JS.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Topology {
+    /// Four walls: top, right, bottom, left.
+    #[default]
+    Square,
+    /// Six walls, pointy-topped with odd rows shifted right by half a cell.
+    Hex,
+    /// Three walls, alternating up- and down-pointing along each row.
+    Triangle,
+}
+
+impl Topology {
+    pub(super) fn cells(self) -> &'static dyn CellTopology {
+        match self {
+            Self::Square => &Square,
+            Self::Hex => &Hex,
+            Self::Triangle => &Triangle,
+        }
+    }
+}
+
+struct Square;
+
+impl CellTopology for Square {
+    fn walls_per_cell(&self) -> usize {
+        4
+    }
+
+    fn across(&self, x: isize, y: isize, wall: usize) -> ((isize, isize), usize) {
+        // Up: wall 0 here, 2 there. Right: 1 and 3. Down: 2 and 0. Left: 3 and 1.
+        let (dx, dy) = [(0, -1), (1, 0), (0, 1), (-1, 0)][wall];
+        ((x + dx, y + dy), (wall + 2) % 4)
+    }
+
+    fn corners(&self, x: usize, y: usize) -> Vec<(f64, f64)> {
+        let (x, y) = (x as f64, y as f64);
+        vec![(x, y), (x + 1.0, y), (x + 1.0, y + 1.0), (x, y + 1.0)]
+    }
+
+    fn extent(&self, width: usize, height: usize) -> (f64, f64) {
+        (width as f64, height as f64)
+    }
+
+    fn near(&self, (px, py): (f64, f64)) -> (isize, isize) {
+        (px.floor() as isize, py.floor() as isize)
+    }
+}
+
+struct Hex;
+
+impl CellTopology for Hex {
+    fn walls_per_cell(&self) -> usize {
+        6
+    }
+
+    fn across(&self, x: isize, y: isize, wall: usize) -> ((isize, isize), usize) {
+        // Walls: north-east, east, south-east, south-west, west, north-west.
+        let shift = y.rem_euclid(2);
+        let (dx, dy) = [
+            (shift, -1),
+            (1, 0),
+            (shift, 1),
+            (shift - 1, 1),
+            (-1, 0),
+            (shift - 1, -1),
+        ][wall];
+        ((x + dx, y + dy), (wall + 3) % 6)
+    }
+
+    fn corners(&self, x: usize, y: usize) -> Vec<(f64, f64)> {
+        let radius = HEX_ROW / 1.5;
+        let cx = x as f64 + 0.5 + if y % 2 == 1 { 0.5 } else { 0.0 };
+        let cy = radius + y as f64 * HEX_ROW;
+        vec![
+            (cx, cy - radius),
+            (cx + 0.5, cy - radius / 2.0),
+            (cx + 0.5, cy + radius / 2.0),
+            (cx, cy + radius),
+            (cx - 0.5, cy + radius / 2.0),
+            (cx - 0.5, cy - radius / 2.0),
+        ]
+    }
+
+    fn extent(&self, width: usize, height: usize) -> (f64, f64) {
+        let radius = HEX_ROW / 1.5;
+        let shifted = if height > 1 { 0.5 } else { 0.0 };
+        (
+            width as f64 + shifted,
+            2.0 * radius + (height as f64 - 1.0) * HEX_ROW,
+        )
+    }
+
+    fn near(&self, (px, py): (f64, f64)) -> (isize, isize) {
+        // The row whose centres lie closest, then the column by those centres
+        let y = ((py - HEX_ROW / 1.5) / HEX_ROW).round() as isize;
+        let shift = if y.rem_euclid(2) == 1 { 0.5 } else { 0.0 };
+        ((px - shift).floor() as isize, y)
+    }
+}
+
+struct Triangle;
+
+impl Triangle {
+    fn points_up(x: isize, y: isize) -> bool {
+        (x + y).rem_euclid(2) == 0
+    }
+}
+
+impl CellTopology for Triangle {
+    fn walls_per_cell(&self) -> usize {
+        3
+    }
+
+    fn across(&self, x: isize, y: isize, wall: usize) -> ((isize, isize), usize) {
+        // Walls: left side, right side, then the base (below when pointing
+        // up, above when pointing down).
+        match wall {
+            0 => ((x - 1, y), 1),
+            1 => ((x + 1, y), 0),
+            _ if Self::points_up(x, y) => ((x, y + 1), 2),
+            _ => ((x, y - 1), 2),
+        }
+    }
+
+    fn corners(&self, x: usize, y: usize) -> Vec<(f64, f64)> {
+        let left = x as f64 * 0.5;
+        let (top, bottom) = (y as f64 * TRIANGLE_ROW, (y + 1) as f64 * TRIANGLE_ROW);
+        if Self::points_up(x as isize, y as isize) {
+            vec![(left, bottom), (left + 0.5, top), (left + 1.0, bottom)]
+        } else {
+            vec![(left, top), (left + 0.5, bottom), (left + 1.0, top)]
+        }
+    }
+
+    fn extent(&self, width: usize, height: usize) -> (f64, f64) {
+        ((width as f64 + 1.0) * 0.5, height as f64 * TRIANGLE_ROW)
+    }
+
+    fn near(&self, (px, py): (f64, f64)) -> (isize, isize) {
+        // Triangles overlap their neighbours' columns by half a width
+        (
+            (2.0 * px).floor() as isize - 1,
+            (py / TRIANGLE_ROW).floor() as isize,
+        )
+    }
+}
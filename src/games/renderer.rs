@@ -0,0 +1,305 @@
+//! High-level view updates shared by both games, and the backends that show
+//! them. Game logic says what changed; a `Renderer` decides how it looks.
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use wasm_bindgen::prelude::*;
+use web_sys::{Document, Element};
+
+const BORDER_FLASH: &str = "1ch solid var(--magma-color)";
+const BORDER_FADE: &str = "0px solid transparent";
+
+/// How one cell (a maze cell or a numeracy bubble) should look.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CellView {
+    pub text: String,
+    /// State classes on top of the cell's base class, e.g. `visited`.
+    pub classes: Vec<String>,
+}
+
+/// Shape of a board of cells, in cell widths.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layout {
+    /// Column count, when the cells tile a plain square grid.
+    pub columns: Option<usize>,
+    /// Corners of each cell, in drawing order. Wall `i` of a cell runs from
+    /// corner `i` to corner `i + 1`.
+    pub cells: Vec<Vec<(f64, f64)>>,
+    /// Width and height of the whole board.
+    pub extent: (f64, f64),
+}
+
+/// Receives view updates from a game.
+pub trait Renderer {
+    /// The board took a new shape; cells are redrawn after this.
+    fn layout_changed(&mut self, layout: &Layout) -> Result<(), JsValue>;
+    fn cell_changed(&mut self, index: usize, view: &CellView) -> Result<(), JsValue>;
+    /// Named values shown beside the board, e.g. `("level", "3")`. Button
+    /// labels are sent this way too, keyed by the button's id.
+    fn stats_changed(&mut self, stats: &[(&str, String)]) -> Result<(), JsValue>;
+    fn timer_changed(&mut self, text: &str) -> Result<(), JsValue>;
+    /// The player walked into wall `wall` of cell `index`.
+    fn wall_hit(&mut self, index: usize, wall: usize) -> Result<(), JsValue>;
+    /// A button or other control, by id, should be shown or hidden.
+    fn control_shown(&mut self, id: &str, visible: bool) -> Result<(), JsValue>;
+    /// Called every few tens of milliseconds, for renderers that animate.
+    fn tick(&mut self, _now: f64) -> Result<(), JsValue> {
+        Ok(())
+    }
+}
+
+/// A renderer a game and its event handlers can all hold.
+pub type SharedRenderer = Rc<RefCell<dyn Renderer>>;
+
+/// Inline style placing a non-square cell by its corners, in `--cell-size` units.
+fn shape_style(corners: &[(f64, f64)]) -> String {
+    let min_x = corners.iter().map(|c| c.0).fold(f64::INFINITY, f64::min);
+    let max_x = corners
+        .iter()
+        .map(|c| c.0)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let min_y = corners.iter().map(|c| c.1).fold(f64::INFINITY, f64::min);
+    let max_y = corners
+        .iter()
+        .map(|c| c.1)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let (w, h) = (max_x - min_x, max_y - min_y);
+    let polygon = corners
+        .iter()
+        .map(|(x, y)| {
+            format!(
+                "{:.2}% {:.2}%",
+                (x - min_x) / w * 100.0,
+                (y - min_y) / h * 100.0
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "left: calc(var(--cell-size) * {min_x:.4}); top: calc(var(--cell-size) * {min_y:.4}); \
+         width: calc(var(--cell-size) * {w:.4}); height: calc(var(--cell-size) * {h:.4}); \
+         clip-path: polygon({polygon})"
+    )
+}
+
+/// Shows cells as child elements of a container, and everything else in
+/// elements found by id.
+pub(crate) struct DomRenderer {
+    document: Document,
+    // Id of the element holding one child per cell
+    container: &'static str,
+    // Class every cell carries
+    base_class: &'static str,
+    layout: Option<Layout>,
+}
+
+impl DomRenderer {
+    pub(crate) fn new(
+        document: Document,
+        container: &'static str,
+        base_class: &'static str,
+    ) -> Self {
+        Self {
+            document,
+            container,
+            base_class,
+            layout: None,
+        }
+    }
+
+    fn cell(&self, index: usize) -> Option<Element> {
+        self.document
+            .get_element_by_id(self.container)?
+            .children()
+            .item(index as u32)
+    }
+
+    fn shaped(&self) -> bool {
+        self.layout
+            .as_ref()
+            .is_some_and(|layout| layout.columns.is_none())
+    }
+}
+
+impl Renderer for DomRenderer {
+    fn layout_changed(&mut self, layout: &Layout) -> Result<(), JsValue> {
+        let Some(container) = self.document.get_element_by_id(self.container) else {
+            return Ok(());
+        };
+        let style = match layout.columns {
+            Some(columns) => format!("grid-template-columns: repeat({columns}, 60px)"),
+            // Shaped cells are positioned absolutely inside a sized box.
+            None => format!(
+                "display: block; width: calc(var(--cell-size) * {:.4}); \
+                 height: calc(var(--cell-size) * {:.4})",
+                layout.extent.0, layout.extent.1
+            ),
+        };
+
+        // Only regenerate grid if the dimensions changed
+        if container.children().length() as usize == layout.cells.len()
+            && container.get_attribute("style").as_deref() == Some(style.as_str())
+        {
+            self.layout = Some(layout.clone());
+            return Ok(());
+        }
+        container.set_attribute("style", &style)?;
+
+        // Clear existing content safely
+        while let Some(child) = container.first_child() {
+            container.remove_child(&child)?;
+        }
+
+        // Create cells only once
+        for corners in &layout.cells {
+            let cell = self.document.create_element("div")?;
+            cell.set_class_name(self.base_class);
+            if layout.columns.is_none() {
+                cell.set_attribute("style", &shape_style(corners))?;
+            }
+            // A text node for the content, then a span for pseudo-elements
+            let span = self.document.create_element("span")?;
+            let content = self.document.create_text_node("");
+            cell.append_child(&content)?;
+            cell.append_child(&span)?;
+            container.append_child(&cell)?;
+        }
+        self.layout = Some(layout.clone());
+        Ok(())
+    }
+
+    fn cell_changed(&mut self, index: usize, view: &CellView) -> Result<(), JsValue> {
+        let Some(cell) = self.cell(index) else {
+            return Ok(());
+        };
+        let mut class = self.base_class.to_string();
+        if self.shaped() {
+            class.push_str(" shaped");
+        }
+        for extra in &view.classes {
+            class.push(' ');
+            class.push_str(extra);
+        }
+        cell.set_class_name(&class);
+
+        // Update text content if it's different; cells built by
+        // `layout_changed` keep theirs in a leading text node.
+        let target = match cell.first_child() {
+            Some(node) if node.node_type() == web_sys::Node::TEXT_NODE => node,
+            _ => cell.into(),
+        };
+        if target.text_content().unwrap_or_default() != view.text {
+            target.set_text_content(Some(&view.text));
+        }
+        Ok(())
+    }
+
+    fn stats_changed(&mut self, stats: &[(&str, String)]) -> Result<(), JsValue> {
+        for (id, value) in stats {
+            if let Some(el) = self.document.get_element_by_id(id) {
+                el.set_text_content(Some(value));
+            }
+        }
+        Ok(())
+    }
+
+    fn timer_changed(&mut self, text: &str) -> Result<(), JsValue> {
+        if let Some(timer_el) = self.document.get_element_by_id("timer") {
+            timer_el.set_text_content(Some(text));
+        }
+        Ok(())
+    }
+
+    fn wall_hit(&mut self, index: usize, wall: usize) -> Result<(), JsValue> {
+        let Some(cell) = self.cell(index) else {
+            return Ok(());
+        };
+        // Determine which border to animate; shaped cells have no borders,
+        // so the whole cell flashes instead.
+        let (border_prop, flash, fade) = if self.shaped() {
+            ("backgroundColor", "var(--magma-color)", "transparent")
+        } else {
+            let side = ["borderTop", "borderRight", "borderBottom", "borderLeft"][wall % 4];
+            (side, BORDER_FLASH, BORDER_FADE)
+        };
+
+        // Build keyframes: from red border to no border.
+        let keyframes = js_sys::Array::new();
+        for (offset, value) in [(0.0, flash), (1.0, fade)] {
+            let frame = js_sys::Object::new();
+            js_sys::Reflect::set(
+                &frame,
+                &JsValue::from_str("offset"),
+                &JsValue::from_f64(offset),
+            )?;
+            js_sys::Reflect::set(
+                &frame,
+                &JsValue::from_str(border_prop),
+                &JsValue::from_str(value),
+            )?;
+            keyframes.push(&frame);
+        }
+        cell.animate_with_f64(Some(&keyframes), 1000.0);
+        Ok(())
+    }
+
+    fn control_shown(&mut self, id: &str, visible: bool) -> Result<(), JsValue> {
+        if let Some(el) = self.document.get_element_by_id(id) {
+            if visible {
+                el.remove_attribute("hidden")?;
+            } else {
+                el.set_attribute("hidden", "")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Keeps the latest view and a log of wall hits in memory, without a DOM,
+/// so UI output can be asserted on in tests or inspected headless.
+#[derive(Debug, Default)]
+pub struct RecordingRenderer {
+    pub layout: Option<Layout>,
+    pub cells: Vec<CellView>,
+    pub stats: HashMap<String, String>,
+    pub timer: String,
+    pub hidden: HashMap<String, bool>,
+    pub wall_hits: Vec<(usize, usize)>,
+}
+
+impl Renderer for RecordingRenderer {
+    fn layout_changed(&mut self, layout: &Layout) -> Result<(), JsValue> {
+        self.cells = vec![CellView::default(); layout.cells.len()];
+        self.layout = Some(layout.clone());
+        Ok(())
+    }
+
+    fn cell_changed(&mut self, index: usize, view: &CellView) -> Result<(), JsValue> {
+        if index >= self.cells.len() {
+            self.cells.resize(index + 1, CellView::default());
+        }
+        self.cells[index] = view.clone();
+        Ok(())
+    }
+
+    fn stats_changed(&mut self, stats: &[(&str, String)]) -> Result<(), JsValue> {
+        for (id, value) in stats {
+            self.stats.insert(id.to_string(), value.clone());
+        }
+        Ok(())
+    }
+
+    fn timer_changed(&mut self, text: &str) -> Result<(), JsValue> {
+        self.timer = text.to_string();
+        Ok(())
+    }
+
+    fn wall_hit(&mut self, index: usize, wall: usize) -> Result<(), JsValue> {
+        self.wall_hits.push((index, wall));
+        Ok(())
+    }
+
+    fn control_shown(&mut self, id: &str, visible: bool) -> Result<(), JsValue> {
+        self.hidden.insert(id.to_string(), !visible);
+        Ok(())
+    }
+}
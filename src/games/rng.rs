@@ -0,0 +1,70 @@
+/// Small deterministic PRNG (Mulberry32) used for maze generation, so the same
+/// seed always rebuilds the same maze, and for numeracy's expressions when
+/// a test needs them to repeat.
+#[derive(Clone, Debug)]
+pub(crate) struct Rng {
+    state: u32,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u32) -> Self {
+        Self { state: seed }
+    }
+
+    pub(crate) fn next_u32(&mut self) -> u32 {
+        self.state = self.state.wrapping_add(0x6D2B_79F5);
+        let mut z = self.state;
+        z = (z ^ (z >> 15)).wrapping_mul(z | 1);
+        z ^= z.wrapping_add((z ^ (z >> 7)).wrapping_mul(z | 61));
+        z ^ (z >> 14)
+    }
+
+    /// Uniform float in `[0, 1)`, a drop-in for `Math::random`.
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        f64::from(self.next_u32()) / 4_294_967_296.0
+    }
+
+    /// Uniform index in `0..n`. `n` must be non-zero.
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next_f64() * n as f64) as usize
+    }
+
+    /// Fisher-Yates shuffle.
+    pub(crate) fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+/// Derives the seed of the next maze in a session from the current one, so a
+/// whole run can be replayed from its first seed.
+pub(crate) fn next_seed(seed: u32) -> u32 {
+    Rng::new(seed).next_u32()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_seed_always_gives_the_same_numbers() {
+        let mut first = Rng::new(42);
+        let mut second = Rng::new(42);
+        for _ in 0..100 {
+            assert_eq!(first.next_u32(), second.next_u32());
+        }
+    }
+
+    #[test]
+    fn next_seed_chains_without_repeating() {
+        let mut seen = std::collections::HashSet::new();
+        let mut seed = 42;
+        for _ in 0..10_000 {
+            assert!(seen.insert(seed), "seed {} came round again", seed);
+            let next = next_seed(seed);
+            assert_eq!(next, Rng::new(seed).next_u32());
+            seed = next;
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! Where both games keep progress and settings. Browsers may refuse
+//! `localStorage` (private browsing, sandboxed iframes), so the page's
+//! storage is picked at start-up: `localStorage` if it works, else
+//! IndexedDB, else memory that lasts until the page closes.
+
+use std::{cell::RefCell, collections::HashMap, fmt::Debug, rc::Rc};
+use wasm_bindgen::prelude::*;
+use web_sys::{
+    console, IdbCursorWithValue, IdbDatabase, IdbOpenDbRequest, IdbRequest, IdbTransactionMode,
+};
+
+const DATABASE: &str = "cognitive-games";
+const STORE: &str = "items";
+
+/// String key/value storage, like `localStorage`.
+pub trait Storage: Debug {
+    /// The value under `key`; None if there is none or it cannot be read.
+    fn get_item(&self, key: &str) -> Option<String>;
+    fn set_item(&mut self, key: &str, value: &str) -> Result<(), JsValue>;
+    fn remove_item(&mut self, key: &str) -> Result<(), JsValue>;
+}
+
+/// Storage the games and their event handlers can all hold.
+pub type SharedStorage = Rc<RefCell<dyn Storage>>;
+
+thread_local! {
+    static INSTALLED: RefCell<Option<SharedStorage>> = const { RefCell::new(None) };
+}
+
+/// The page's storage: the one installed, or else `localStorage` if it
+/// works and memory if not.
+pub fn shared() -> SharedStorage {
+    INSTALLED.with(|installed| {
+        installed
+            .borrow_mut()
+            .get_or_insert_with(|| match LocalStorage::open() {
+                Some(local) => Rc::new(RefCell::new(local)),
+                None => Rc::new(RefCell::new(MemoryStorage::default())),
+            })
+            .clone()
+    })
+}
+
+/// Makes `storage` the one every game uses from now on, e.g. to embed the
+/// games somewhere with storage of its own.
+pub fn install(storage: SharedStorage) {
+    INSTALLED.with(|installed| *installed.borrow_mut() = Some(storage));
+}
+
+/// Picks the page's storage, falling back from `localStorage` to IndexedDB
+/// to memory, and calls `on_ready` once it holds any saved data.
+pub(crate) fn open(on_ready: impl FnOnce() + 'static) {
+    if let Some(local) = LocalStorage::open() {
+        install(Rc::new(RefCell::new(local)));
+        on_ready();
+        return;
+    }
+    IndexedDbStorage::open(move |database| {
+        match database {
+            Some(database) => install(Rc::new(RefCell::new(database))),
+            None => {
+                console::log_1(
+                    &"No persistent storage; progress lasts until the page closes".into(),
+                );
+                install(Rc::new(RefCell::new(MemoryStorage::default())));
+            }
+        }
+        on_ready();
+    });
+}
+
+/// The page's `localStorage`.
+#[derive(Debug)]
+pub(crate) struct LocalStorage(web_sys::Storage);
+
+impl LocalStorage {
+    /// None if the browser has no `localStorage` or refuses writes to it.
+    pub(crate) fn open() -> Option<Self> {
+        let storage = web_sys::window()?.local_storage().ok()??;
+        // Some private modes hand out a store that throws on every write
+        storage.set_item("storage_probe", "1").ok()?;
+        storage.remove_item("storage_probe").ok()?;
+        Some(Self(storage))
+    }
+}
+
+impl Storage for LocalStorage {
+    fn get_item(&self, key: &str) -> Option<String> {
+        self.0.get_item(key).ok().flatten()
+    }
+
+    fn set_item(&mut self, key: &str, value: &str) -> Result<(), JsValue> {
+        self.0.set_item(key, value)
+    }
+
+    fn remove_item(&mut self, key: &str) -> Result<(), JsValue> {
+        self.0.remove_item(key)
+    }
+}
+
+/// An IndexedDB object store, read in full when opened so reads can be
+/// answered at once. Writes update that copy and are stored in the
+/// background.
+#[derive(Debug)]
+pub(crate) struct IndexedDbStorage {
+    database: IdbDatabase,
+    cache: HashMap<String, String>,
+}
+
+impl IndexedDbStorage {
+    /// Opens the database and reads it, then passes it to `on_ready`, or
+    /// None if IndexedDB is missing or fails.
+    pub(crate) fn open(on_ready: impl FnOnce(Option<Self>) + 'static) {
+        let on_ready = Rc::new(RefCell::new(Some(on_ready)));
+        let finish = move |database: Option<Self>| {
+            if let Some(on_ready) = on_ready.borrow_mut().take() {
+                on_ready(database);
+            }
+        };
+
+        let request = web_sys::window()
+            .and_then(|window| window.indexed_db().ok().flatten())
+            .and_then(|factory| factory.open_with_u32(DATABASE, 1).ok());
+        let Some(request) = request else {
+            finish(None);
+            return;
+        };
+
+        let on_upgrade = Closure::once_into_js(move |event: web_sys::Event| {
+            let database = event
+                .target()
+                .and_then(|target| target.dyn_into::<IdbOpenDbRequest>().ok())
+                .and_then(|request| request.result().ok())
+                .and_then(|result| result.dyn_into::<IdbDatabase>().ok());
+            if let Some(database) = database {
+                let _ = database.create_object_store(STORE);
+            }
+        });
+        request.set_onupgradeneeded(Some(on_upgrade.unchecked_ref()));
+
+        // Each event gets its own closure, as a one-shot closure cannot be
+        // called twice
+        let on_failure = || {
+            let finish = finish.clone();
+            Closure::once_into_js(move || finish(None))
+        };
+        request.set_onerror(Some(on_failure().unchecked_ref()));
+        request.set_onblocked(Some(on_failure().unchecked_ref()));
+
+        let opened = request.clone();
+        let on_success = Closure::once_into_js(move || {
+            let database = opened
+                .result()
+                .ok()
+                .and_then(|result| result.dyn_into::<IdbDatabase>().ok());
+            match database {
+                Some(database) => Self::load(database, finish),
+                None => finish(None),
+            }
+        });
+        request.set_onsuccess(Some(on_success.unchecked_ref()));
+    }
+
+    /// Reads every entry with a cursor, then passes the store to `finish`.
+    fn load(database: IdbDatabase, finish: impl Fn(Option<Self>) + Clone + 'static) {
+        let request = database
+            .transaction_with_str(STORE)
+            .and_then(|transaction| transaction.object_store(STORE))
+            .and_then(|store| store.open_cursor());
+        let Ok(request) = request else {
+            finish(None);
+            return;
+        };
+
+        let cache = Rc::new(RefCell::new(HashMap::new()));
+        let cursor_request = request.clone();
+        let on_error = {
+            let finish = finish.clone();
+            Closure::once_into_js(move || finish(None))
+        };
+        let on_entry = Closure::wrap(Box::new(move || {
+            let cursor = cursor_request
+                .result()
+                .ok()
+                .and_then(|result| result.dyn_into::<IdbCursorWithValue>().ok());
+            match cursor {
+                Some(cursor) => {
+                    let key = cursor.key().ok().and_then(|key| key.as_string());
+                    let value = cursor.value().ok().and_then(|value| value.as_string());
+                    if let (Some(key), Some(value)) = (key, value) {
+                        cache.borrow_mut().insert(key, value);
+                    }
+                    if cursor.continue_().is_err() {
+                        finish(None);
+                    }
+                }
+                // The cursor is past the last entry
+                None => finish(Some(Self {
+                    database: database.clone(),
+                    cache: cache.take(),
+                })),
+            }
+        }) as Box<dyn FnMut()>);
+        request.set_onsuccess(Some(on_entry.as_ref().unchecked_ref()));
+        request.set_onerror(Some(on_error.unchecked_ref()));
+        on_entry.forget();
+    }
+
+    fn store(&self) -> Result<web_sys::IdbObjectStore, JsValue> {
+        self.database
+            .transaction_with_str_and_mode(STORE, IdbTransactionMode::Readwrite)?
+            .object_store(STORE)
+    }
+
+    /// Logs the background write `request` if it fails, as nothing waits
+    /// on it to report the failure.
+    fn log_failure(request: &IdbRequest, action: &'static str, key: &str) {
+        let message = format!("Could not {action} {key} in IndexedDB");
+        let on_error = Closure::once_into_js(move |event: web_sys::Event| {
+            console::error_2(&message.into(), &event);
+        });
+        request.set_onerror(Some(on_error.unchecked_ref()));
+    }
+}
+
+impl Storage for IndexedDbStorage {
+    fn get_item(&self, key: &str) -> Option<String> {
+        self.cache.get(key).cloned()
+    }
+
+    fn set_item(&mut self, key: &str, value: &str) -> Result<(), JsValue> {
+        self.cache.insert(key.to_string(), value.to_string());
+        let request = self
+            .store()?
+            .put_with_key(&JsValue::from_str(value), &JsValue::from_str(key))?;
+        Self::log_failure(&request, "save", key);
+        Ok(())
+    }
+
+    fn remove_item(&mut self, key: &str) -> Result<(), JsValue> {
+        self.cache.remove(key);
+        let request = self.store()?.delete(&JsValue::from_str(key))?;
+        Self::log_failure(&request, "remove", key);
+        Ok(())
+    }
+}
+
+/// Storage that lasts as long as the page, or the process when headless.
+#[derive(Debug, Default)]
+pub struct MemoryStorage(HashMap<String, String>);
+
+impl Storage for MemoryStorage {
+    fn get_item(&self, key: &str) -> Option<String> {
+        self.0.get(key).cloned()
+    }
+
+    fn set_item(&mut self, key: &str, value: &str) -> Result<(), JsValue> {
+        self.0.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn remove_item(&mut self, key: &str) -> Result<(), JsValue> {
+        self.0.remove(key);
+        Ok(())
+    }
+}
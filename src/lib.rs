@@ -1,8 +1,12 @@
 mod games;
 
+pub use games::clock::Clock;
 pub use games::numeracy::Numeracy;
-pub use games::perception::Perception;
+pub use games::perception::{Algorithm, Direction, Perception, RenderMode, Topology, WallPenalty};
+pub use games::renderer::{CellView, Layout, RecordingRenderer, Renderer, SharedRenderer};
+pub use games::storage::{install as install_storage, MemoryStorage, SharedStorage, Storage};
 use wasm_bindgen::{prelude::*, JsValue};
+use web_sys::console;
 
 #[cfg(feature = "wee_alloc")]
 #[global_allocator]
@@ -18,15 +22,15 @@ pub fn main_js() -> Result<(), JsValue> {
     let location = window.location();
     let path = location.pathname().expect("pathname should exist");
 
-    // Return the created instance rather than discarding it
-    match path.as_str() {
-        "/numeracy" => {
-            let game = Numeracy::new()?;
-            game.start()?;
+    // Games read saved progress as they start, so wait for storage to load
+    games::storage::open(move || {
+        let started = match path.as_str() {
+            "/numeracy" => Numeracy::new().and_then(|game| game.start()),
+            _ => Perception::new().map(drop),
+        };
+        if let Err(e) = started {
+            console::error_1(&e);
         }
-        _ => {
-            Perception::new()?;
-        }
-    }
+    });
     Ok(())
 }